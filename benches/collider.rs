@@ -0,0 +1,142 @@
+// Copyright 2016-2018 Matthew D. Michelotti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use collider::geom::{v2, Shape, Vec2};
+use collider::{Collider, HbEvent, HbId, HbProfile, HbVel, Hitbox};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+#[derive(Copy, Clone, Debug)]
+struct BenchHbProfile {
+    id: HbId,
+}
+
+impl HbProfile for BenchHbProfile {
+    fn id(&self) -> HbId {
+        self.id
+    }
+    fn can_interact(&self, _other: &BenchHbProfile) -> bool {
+        true
+    }
+}
+
+// a deterministic pseudo-random sequence, so that runs are comparable
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn vel(&mut self) -> Vec2 {
+        v2(self.next() * 2.0 - 1.0, self.next() * 2.0 - 1.0)
+    }
+}
+
+fn hitboxes(count: u64) -> Vec<(BenchHbProfile, Hitbox)> {
+    let mut rng = Lcg(count);
+    let side = (count as f64).sqrt().ceil() as u64;
+    (0..count)
+        .map(|id| {
+            let pos = v2((id % side) as f64 * 3.0, (id / side) as f64 * 3.0);
+            let shape = if id % 2 == 0 {
+                Shape::square(1.0)
+            } else {
+                Shape::circle(1.0)
+            };
+            (BenchHbProfile { id }, shape.place(pos).moving(rng.vel()))
+        })
+        .collect()
+}
+
+fn populate(count: u64) -> Collider<BenchHbProfile> {
+    let mut collider = Collider::new(4.0, 0.01);
+    for (profile, hitbox) in hitboxes(count) {
+        collider.add_hitbox(profile, hitbox);
+    }
+    collider
+}
+
+fn populate_bounded(count: u64) -> Collider<BenchHbProfile> {
+    let side = (count as f64).sqrt().ceil() * 3.0;
+    let bounds = Shape::square(side + 20.0).place(v2(side, side) * 0.5);
+    let mut collider = Collider::with_bounds(4.0, 0.01, bounds);
+    for (profile, hitbox) in hitboxes(count) {
+        collider.add_hitbox(profile, hitbox);
+    }
+    collider
+}
+
+fn populate_batch(count: u64) -> Collider<BenchHbProfile> {
+    let mut collider = Collider::new(4.0, 0.01);
+    collider.add_hitboxes(hitboxes(count));
+    collider
+}
+
+// advances the simulation, changing the velocity of some hitboxes each frame
+fn simulate(collider: &mut Collider<BenchHbProfile>, count: u64, frames: u32) {
+    let mut rng = Lcg(count + 1);
+    for frame in 1..=frames {
+        let frame_time = frame as f64 * 0.1;
+        while collider.time() < frame_time {
+            let time = collider.next_time().min(frame_time);
+            collider.set_time(time);
+            while let Some((event, profile_1, profile_2)) = collider.next() {
+                if event == HbEvent::Collide {
+                    collider.set_hitbox_vel(profile_1.id, HbVel::moving(rng.vel()));
+                    collider.set_hitbox_vel(profile_2.id, HbVel::moving(rng.vel()));
+                }
+            }
+        }
+        for _ in 0..count / 10 {
+            let id = (rng.next() * count as f64) as HbId;
+            collider.set_hitbox_vel(id, HbVel::moving(rng.vel()));
+        }
+    }
+}
+
+fn bench_collider(c: &mut Criterion) {
+    for &count in &[500, 5000] {
+        c.bench_function(&format!("populate {}", count), |b| {
+            b.iter(|| populate(count))
+        });
+        c.bench_function(&format!("populate batch {}", count), |b| {
+            b.iter(|| populate_batch(count))
+        });
+        c.bench_function(&format!("simulate {}", count), |b| {
+            b.iter_batched(
+                || populate(count),
+                |mut collider| simulate(&mut collider, count, 10),
+                BatchSize::LargeInput,
+            )
+        });
+        c.bench_function(&format!("simulate bounded {}", count), |b| {
+            b.iter_batched(
+                || populate_bounded(count),
+                |mut collider| simulate(&mut collider, count, 10),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_collider
+}
+criterion_main!(benches);
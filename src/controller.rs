@@ -0,0 +1,300 @@
+// Copyright 2016-2018 Matthew D. Michelotti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Module containing a kinematic character controller built on top of
+//! `Collider`.
+
+use crate::core::{Collider, HbEvent, HbId, HbProfile, HbVel};
+use crate::geom::{v2, PlacedShape, Vec2};
+use crate::util::TightSet;
+use std::f64;
+
+// velocities that move into a contact surface slower than this are treated as
+// sliding along it, to absorb rounding error from repeated projections
+const CONTACT_TOLERANCE: f64 = 1e-9;
+
+/// Drives the velocity of a single hitbox in a `Collider` so that it walks,
+/// jumps and falls like a platformer character.
+///
+/// The controller tracks the hitboxes that its character is touching, and
+/// removes any component of the character's velocity that would move it into
+/// one of them. Since Collider reports a `Collide` event at the exact moment
+/// that two hitboxes touch, the character stops exactly at walls and floors,
+/// regardless of its speed.
+///
+/// The user remains responsible for advancing the collider. Each event
+/// returned by `Collider::next` that involves the character and a solid
+/// obstacle should be passed to `handle_event`, and `update` should be called
+/// regularly to apply gravity. The controller owns the `HbVel` of its hitbox,
+/// so the user should not call `Collider::set_hitbox_vel` for it directly. If a
+/// hitbox the character is touching is removed from the collider, the
+/// corresponding `Separate` event should be passed to `handle_event` as well.
+///
+/// Up is taken to be the +y direction.
+#[derive(Clone, Debug)]
+pub struct CharacterController {
+    /// Horizontal speed of the character when walking at full speed.
+    pub walk_speed: f64,
+
+    /// Initial upward speed of a jump.
+    pub jump_speed: f64,
+
+    /// Downward acceleration applied while the character is not grounded.
+    pub gravity: f64,
+
+    /// The steepest slope, in radians, that the character can stand on.
+    /// Surfaces steeper than this are treated as walls. Defaults to 45 degrees.
+    pub max_slope: f64,
+
+    /// The greatest height of a ledge that the character will automatically
+    /// step up onto while walking into it. Defaults to `0.0`.
+    ///
+    /// The space above the ledge is not checked before stepping; if the
+    /// stepped-up character overlaps another hitbox, this is reported as a
+    /// `Collide` event like any other.
+    pub step_height: f64,
+
+    id: HbId,
+    walk: f64,
+    vel_y: f64,
+    grounded: bool,
+    contacts: TightSet<HbId>,
+}
+
+impl CharacterController {
+    /// Constructs a controller for the hitbox with the given `id`, which must
+    /// already have been added to the collider.
+    pub fn new(id: HbId, walk_speed: f64, jump_speed: f64, gravity: f64) -> CharacterController {
+        CharacterController {
+            walk_speed,
+            jump_speed,
+            gravity,
+            max_slope: f64::consts::FRAC_PI_4,
+            step_height: 0.0,
+            id,
+            walk: 0.0,
+            vel_y: 0.0,
+            grounded: false,
+            contacts: TightSet::new(),
+        }
+    }
+
+    /// Returns the id of the character's hitbox.
+    pub fn id(&self) -> HbId {
+        self.id
+    }
+
+    /// Returns true if the character was standing on a surface that is no
+    /// steeper than `max_slope` when its velocity was last applied.
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /// Returns the ids of the hitboxes that the character is touching.
+    pub fn contacts(&self) -> Vec<HbId> {
+        self.contacts.iter().cloned().collect()
+    }
+
+    /// Sets the walking direction, from `-1.0` (full speed towards -x) to
+    /// `1.0` (full speed towards +x).
+    ///
+    /// Takes effect the next time that `update` or `handle_event` is called.
+    pub fn walk(&mut self, dir: f64) {
+        self.walk = dir.clamp(-1.0, 1.0);
+    }
+
+    /// Makes the character jump if it is grounded, returning true if it did.
+    ///
+    /// Takes effect the next time that `update` or `handle_event` is called.
+    pub fn jump(&mut self) -> bool {
+        if self.grounded {
+            self.grounded = false;
+            self.vel_y = self.jump_speed;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Advances the controller by `dt` time units, applying gravity if the
+    /// character is airborne, and updates the velocity of the character's
+    /// hitbox.
+    ///
+    /// Gravity is applied in discrete steps, so the character follows a
+    /// piecewise linear path between calls. `dt` may be `0.0` to apply changes
+    /// from `walk` or `jump` immediately.
+    pub fn update<P: HbProfile>(&mut self, collider: &mut Collider<P>, dt: f64) {
+        if !self.grounded {
+            self.vel_y -= self.gravity * dt;
+        }
+        self.apply(collider);
+    }
+
+    /// Handles an event returned by `Collider::next`, and updates the velocity
+    /// of the character's hitbox if the event involves the character.
+    ///
+    /// Returns true if the event involves the character. The user should only
+    /// pass events for hitboxes that the character cannot pass through.
+    /// Proximity, containment and tile events are ignored, returning false.
+    pub fn handle_event<P: HbProfile>(
+        &mut self,
+        collider: &mut Collider<P>,
+        event: HbEvent,
+        profile_1: &P,
+        profile_2: &P,
+    ) -> bool {
+        let other_id = if profile_1.id() == self.id {
+            profile_2.id()
+        } else if profile_2.id() == self.id {
+            profile_1.id()
+        } else {
+            return false;
+        };
+        match event {
+            HbEvent::Collide => {
+                self.contacts.insert(other_id);
+            }
+            HbEvent::Separate => {
+                self.contacts.remove(&other_id);
+            }
+            HbEvent::Enter
+            | HbEvent::Exit
+            | HbEvent::Contain
+            | HbEvent::Uncontain
+            | HbEvent::TileCollide(_)
+            | HbEvent::TileSeparate(_) => return false,
+        }
+        self.apply(collider);
+        true
+    }
+
+    fn apply<P: HbProfile>(&mut self, collider: &mut Collider<P>) {
+        let mut shape = collider.get_hitbox(self.id).value;
+        let mut normals = self.contact_normals(collider, &shape);
+        if self.step_height > 0.0 && self.walk != 0.0 && self.ground_normal(&normals).is_some() {
+            if let Some(rise) = self.step_rise(collider, &shape, &normals) {
+                // nudge the character onto the ledge so that the ledge is
+                // below it rather than beside it
+                shape.pos += v2(collider.padding() * self.walk.signum(), rise);
+                collider.set_hitbox_pos(self.id, shape.pos);
+                normals = self.contact_normals(collider, &shape);
+            }
+        }
+
+        let walk_vel = self.walk * self.walk_speed;
+        let ground = self.ground_normal(&normals).filter(|_| self.vel_y <= 0.0);
+        self.grounded = ground.is_some();
+        let vel = match ground {
+            Some(normal) => {
+                self.vel_y = 0.0;
+                v2(normal.y, -normal.x) * walk_vel
+            }
+            None => v2(walk_vel, self.vel_y),
+        };
+        let vel = constrain(vel, &normals);
+        if self.vel_y > 0.0 && vel.y < self.vel_y {
+            // bumped into a ceiling
+            self.vel_y = vel.y.max(0.0);
+        }
+        collider.set_hitbox_vel(self.id, HbVel::moving(vel));
+    }
+
+    // returns the normals pointing from each contact towards the character
+    fn contact_normals<P: HbProfile>(
+        &self,
+        collider: &Collider<P>,
+        shape: &PlacedShape,
+    ) -> Vec<(HbId, Vec2)> {
+        self.contacts
+            .iter()
+            .map(|&id| {
+                let other = collider.get_hitbox(id).value;
+                (id, shape.normal_from(&other).dir())
+            })
+            .collect()
+    }
+
+    // returns the most upward-facing normal among contacts that can be stood on
+    fn ground_normal(&self, normals: &[(HbId, Vec2)]) -> Option<Vec2> {
+        let min_y = self.max_slope.cos();
+        normals
+            .iter()
+            .map(|&(_, normal)| normal)
+            .filter(|normal| normal.y >= min_y)
+            .fold(None, |best: Option<Vec2>, normal| match best {
+                Some(best) if best.y >= normal.y => Some(best),
+                _ => Some(normal),
+            })
+    }
+
+    // returns the height the character must rise to step onto every wall it is
+    // walking into, or None if there are no such walls or any is too high
+    fn step_rise<P: HbProfile>(
+        &self,
+        collider: &Collider<P>,
+        shape: &PlacedShape,
+        normals: &[(HbId, Vec2)],
+    ) -> Option<f64> {
+        let min_y = self.max_slope.cos();
+        let mut result = None;
+        for &(id, normal) in normals {
+            if normal.y.abs() < min_y && normal.x * self.walk < 0.0 {
+                let rise = collider.get_hitbox(id).value.max_y() - shape.min_y();
+                if rise <= 0.0 || rise > self.step_height {
+                    return None;
+                }
+                result = Some(rise.max(result.unwrap_or(0.0)));
+            }
+        }
+        result
+    }
+}
+
+// removes the components of vel that move into any of the contact normals
+fn constrain(mut vel: Vec2, normals: &[(HbId, Vec2)]) -> Vec2 {
+    for &(_, normal) in normals {
+        let approach = vel * normal;
+        if approach < 0.0 {
+            vel -= normal * approach;
+        }
+    }
+    if normals
+        .iter()
+        .any(|&(_, normal)| vel * normal < -CONTACT_TOLERANCE)
+    {
+        // wedged between surfaces, such as in a corner
+        Vec2::zero()
+    } else {
+        vel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constrain() {
+        let floor = (0, v2(0.0, 1.0));
+        let wall = (1, v2(-1.0, 0.0));
+        assert_eq!(constrain(v2(2.0, -3.0), &[floor]), v2(2.0, 0.0));
+        assert_eq!(constrain(v2(2.0, 3.0), &[floor]), v2(2.0, 3.0));
+        assert_eq!(constrain(v2(2.0, -3.0), &[floor, wall]), v2(0.0, 0.0));
+        assert_eq!(constrain(v2(-2.0, -3.0), &[floor, wall]), v2(-2.0, 0.0));
+
+        let slope = (2, v2(-1.0, 1.0).normalize().unwrap());
+        let vel = constrain(v2(2.0, 0.0), &[floor, slope]);
+        assert!(vel.dist(&v2(1.0, 1.0)) < 1e-9);
+    }
+}
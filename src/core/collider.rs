@@ -15,10 +15,15 @@
 use crate::core::dur_hitbox::DurHitbox;
 use crate::core::events::{EventKey, EventKeysMap, EventManager, InternalEvent};
 use crate::core::grid::Grid;
-use crate::core::{HbGroup, HbId, HbProfile, HbVel, Hitbox, HIGH_TIME};
-use crate::geom::PlacedShape;
-use crate::util::TightSet;
-use fnv::FnvHashMap;
+use crate::core::slab::Slab;
+use crate::core::{
+    HbGroup, HbId, HbProfile, HbSlot, HbVel, Hitbox, Orbit, TileCoord, TileLayer, HIGH_TIME,
+};
+use crate::geom::{self, CardMask, PlacedShape, ShapeKind, Vec2};
+use crate::util::{self, TightSet};
+use fnv::{FnvHashMap, FnvHashSet};
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 use std::mem;
 
 // TODO check that floating point values are within a good range when adding/updating hitboxes
@@ -28,11 +33,19 @@ use std::mem;
 /// Collider manages events using a "simulation time" that the user updates as
 /// necessary. This time starts at `0.0`.
 pub struct Collider<P: HbProfile> {
-    hitboxes: FnvHashMap<HbId, HitboxInfo<P>>,
+    hitboxes: Slab<HitboxInfo<P>>,
+    slots: FnvHashMap<HbId, HbSlot>,
     time: f64,
     grid: Grid,
     padding: f64,
     events: EventManager,
+    tiles: Option<TileLayer>,
+    tile_profile: Option<P>,
+    bounds: Option<PlacedShape>,
+    report_out_of_bounds: bool,
+    bounds_events: VecDeque<P>,
+    pair_jobs: Vec<PairJob>,
+    cellmates: FnvHashSet<HbSlot>,
 }
 
 impl<P: HbProfile> Collider<P> {
@@ -40,10 +53,13 @@ impl<P: HbProfile> Collider<P> {
     ///
     /// To reduce the number of overlaps that are tested, hitboxes are placed in
     /// a sparse grid structure behind the scenes. `cell_width` is the width of
-    /// the cells used in that grid. If your game has a similar grid concept,
-    /// then it is usually a good choice to use the same cell width as that
-    /// grid. Otherwise, a good choice is to use a width that is slightly larger
-    /// than most of the hitboxes.
+    /// the finest cells used in that grid. Hitboxes that are wider than this
+    /// are placed in coarser levels of the grid, where each level has cells
+    /// twice as wide as the level below it, so that they only occupy a few
+    /// cells. If your game has a similar grid concept, then it is usually a
+    /// good choice to use the same cell width as that grid. Otherwise, a good
+    /// choice is to use a width that is slightly larger than most of the
+    /// smaller hitboxes.
     ///
     /// Collider generates both `Collide` and `Separate` events. However, due to
     /// numerical error, it is important that two hitboxes be a certain small
@@ -55,24 +71,183 @@ impl<P: HbProfile> Collider<P> {
     /// the user, perhaps a fraction of a "pixel."
     ///
     /// Another restriction introduced by `padding` is that hitboxes are not
-    /// allowed to have a width or height smaller than `padding`.
+    /// allowed to have a width or height smaller than `padding`. Individual
+    /// hitboxes may use a different padding with `HbProfile::padding`.
     pub fn new(cell_width: f64, padding: f64) -> Collider<P> {
         assert!(cell_width > padding, "requires cell_width > padding");
         assert!(padding > 0.0, "requires padding > 0.0");
         Collider {
-            hitboxes: FnvHashMap::default(),
+            hitboxes: Slab::new(),
+            slots: FnvHashMap::default(),
             time: 0.0,
             grid: Grid::new(cell_width),
             padding,
             events: EventManager::new(),
+            tiles: None,
+            tile_profile: None,
+            bounds: None,
+            report_out_of_bounds: false,
+            bounds_events: VecDeque::new(),
+            pair_jobs: Vec::new(),
+            cellmates: FnvHashSet::default(),
         }
     }
 
+    /// Constructs a new `Collider` instance for a world with fixed bounds.
+    ///
+    /// This is the same as `Collider::new`, except that the grid cells that
+    /// cover `bounds` are stored in flat arrays rather than a sparse hash map,
+    /// which makes updates and queries faster. Hitboxes are not required to
+    /// stay inside `bounds`, but any hitbox that is not entirely inside is
+    /// tested against every other hitbox in its interact groups, so this
+    /// should be uncommon. Out-of-bounds events may be enabled with
+    /// `self.set_out_of_bounds_events`.
+    ///
+    /// The memory used by the grid is proportional to the area of `bounds`
+    /// divided by the square of `cell_width`, for each group in use.
+    pub fn with_bounds(cell_width: f64, padding: f64, bounds: PlacedShape) -> Collider<P> {
+        let mut collider = Collider::new(cell_width, padding);
+        let bounds = bounds.as_rect();
+        collider.grid = Grid::with_bounds(cell_width, &bounds);
+        collider.bounds = Some(bounds);
+        collider
+    }
+
+    /// Returns the bounds that were used to construct this collider, if any.
+    pub fn bounds(&self) -> Option<PlacedShape> {
+        self.bounds
+    }
+
+    /// Sets whether hitboxes report when they leave the bounds of the
+    /// collider.
+    ///
+    /// When enabled, a hitbox that is not entirely inside the bounds is queued
+    /// up to be retrieved with `self.next_bounds_event()`, at the time that it
+    /// first extends outside of them (or immediately, if it is already outside
+    /// when added or when events are enabled). A hitbox will not be reported
+    /// again until it has been updated while entirely inside the bounds.
+    /// Only hitboxes with a group are reported. Panics if the collider was not
+    /// constructed with `Collider::with_bounds`.
+    pub fn set_out_of_bounds_events(&mut self, enabled: bool) {
+        assert!(self.bounds.is_some(), "collider has no bounds");
+        if self.report_out_of_bounds == enabled {
+            return;
+        }
+        self.report_out_of_bounds = enabled;
+        let slots: Vec<HbSlot> = self
+            .hitboxes
+            .iter()
+            .filter(|(_, info)| info.profile.group().is_some())
+            .map(|(slot, _)| slot)
+            .collect();
+        for slot in slots {
+            self.internal_update_hitbox(slot, None, None);
+        }
+    }
+
+    /// Returns the profile of the next hitbox that has left the bounds of the
+    /// collider, or `None` if there are none.
+    ///
+    /// These events are queued up as they occur while calling `self.next()`,
+    /// so this should be called after `self.next()` returns `None`.
+    pub fn next_bounds_event(&mut self) -> Option<P> {
+        self.bounds_events.pop_front()
+    }
+
     /// Returns the current simulation time.
     pub fn time(&self) -> f64 {
         self.time
     }
 
+    /// Returns the padding that was used to construct this collider, which is
+    /// used by hitboxes that do not have their own padding.
+    pub fn padding(&self) -> f64 {
+        self.padding
+    }
+
+    /// Subtracts `offset` from the simulation time and from every time that
+    /// the collider holds, including the `end_time` of each hitbox velocity
+    /// and the times of all pending events.
+    ///
+    /// The relative timing of events is preserved, so the simulation continues
+    /// as before, but with `self.time()` reduced by `offset`. Precision of
+    /// floating point times is lost as they grow, so long-running simulations
+    /// should periodically rebase the time back towards `0.0`. Any times held
+    /// by the user, such as velocity end times, must be adjusted to match.
+    pub fn rebase_time(&mut self, offset: f64) {
+        assert!(offset.is_finite(), "requires finite offset");
+        // events at or past `HIGH_TIME` are never queued, so moving hitboxes
+        // that are solved that far ahead may be missing events that the
+        // rebase brings into range
+        let unsolved: Vec<HbSlot> = self
+            .hitboxes
+            .iter()
+            .filter(|(_, info)| {
+                offset > 0.0 && info.hitbox.vel.end_time >= HIGH_TIME && !info.hitbox.vel.is_still()
+            })
+            .map(|(slot, _)| slot)
+            .collect();
+        self.time -= offset;
+        self.events.rebase_time(offset);
+        for (_, info) in self.hitboxes.iter_mut() {
+            info.start_time -= offset;
+            info.pub_end_time -= offset;
+            info.hitbox.vel.end_time -= offset;
+            for waypoint in info.path.iter_mut() {
+                waypoint.0 -= offset;
+            }
+            let event_keys = mem::replace(&mut info.event_keys, TightSet::new());
+            for key in event_keys.iter() {
+                info.event_keys.insert(key.rebased(offset));
+            }
+        }
+        for slot in unsolved {
+            self.internal_update_hitbox(slot, None, None);
+        }
+    }
+
+    /// Moves the origin of the coordinate system to `delta`, subtracting
+    /// `delta` from the position of every hitbox, the tile layer, and the
+    /// bounds of the collider.
+    ///
+    /// Since all hitboxes move together, overlaps and the times of pending
+    /// events are unchanged. Precision of floating point positions is lost as
+    /// they grow, so games with very large worlds should periodically shift
+    /// the origin towards the area of interest. Any positions held by the
+    /// user must be adjusted to match.
+    pub fn shift_origin(&mut self, delta: Vec2) {
+        assert!(
+            delta.x.is_finite() && delta.y.is_finite(),
+            "requires finite delta"
+        );
+        if let Some(ref mut tiles) = self.tiles {
+            tiles.shift_origin(delta);
+        }
+        if let Some(ref mut bounds) = self.bounds {
+            bounds.pos -= delta;
+        }
+        let cell_width = self.grid.cell_width();
+        self.grid = match self.bounds {
+            Some(ref bounds) => Grid::with_bounds(cell_width, bounds),
+            None => Grid::new(cell_width),
+        };
+        for (slot, info) in self.hitboxes.iter_mut() {
+            info.hitbox.value.pos -= delta;
+            if let Some(ref mut orbit) = info.hitbox.vel.orbit {
+                orbit.center -= delta;
+            }
+            for waypoint in info.path.iter_mut() {
+                waypoint.1 -= delta;
+            }
+            if let Some(group) = info.profile.group() {
+                let hitbox = info.hitbox.to_dur_hitbox(info.start_time);
+                let hitbox = info.grid_hitbox(&hitbox);
+                self.grid
+                    .update_hitbox(slot, group, None, Some(&hitbox), &[]);
+            }
+        }
+    }
+
     /// Returns the time at which `self.next()` needs to be called again.
     ///
     /// Even if `self.next_time() == self.time()`, there is a chance that
@@ -101,104 +276,396 @@ impl<P: HbProfile> Collider<P> {
         self.time = time;
     }
 
-    /// Processes and returns the next `Collide` or `Separate` event, or returns
-    /// `None` if there are no more events that occured at the given time
-    /// (although an internal event might have been processed if `None` is
-    /// returned). Will always return `None` if `self.next_time() >
+    /// Processes and returns the next `Collide`, `Separate`, `Enter` or `Exit`
+    /// event, or returns `None` if there are no more events that occured at the
+    /// given time (although an internal event might have been processed if
+    /// `None` is returned). Will always return `None` if `self.next_time() >
     /// self.time()`.
     ///
-    /// The returned value is a tuple, denoting the type of event (`Collide` or
-    /// `Separate`) and the two hitbox profiles involved, in increasing order by
-    /// `HbId`.
+    /// The returned value is a tuple, denoting the type of event and the two
+    /// hitbox profiles involved, in increasing order by `HbId`. The exceptions
+    /// are `Contain` and `Uncontain` events, where the container is given
+    /// first and the hitbox inside of it second.
+    ///
+    /// `TileCollide` and `TileSeparate` events between a hitbox and the tile
+    /// layer are returned here as well, with the profile of the hitbox first
+    /// and the profile that was given to `self.set_tile_layer` second.
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<(HbEvent, P, P)> {
         while let Some(event) = self.events.next(self.time, &mut self.hitboxes) {
-            if let Some((event, id_1, id_2)) = self.process_event(event) {
-                return Some((
-                    event,
-                    self.hitboxes[&id_1].profile,
-                    self.hitboxes[&id_2].profile,
-                ));
+            match event {
+                InternalEvent::TileCollide(slot, coord) => {
+                    return Some(self.process_tile_event(slot, HbEvent::TileCollide(coord)));
+                }
+                InternalEvent::TileSeparate(slot, coord) => {
+                    return Some(self.process_tile_event(slot, HbEvent::TileSeparate(coord)));
+                }
+                _ => (),
+            }
+            if let Some((event, slot_1, slot_2)) = self.process_event(event) {
+                let (profile_1, profile_2) =
+                    (self.hitboxes[slot_1].profile, self.hitboxes[slot_2].profile);
+                return Some(match event {
+                    HbEvent::Contain | HbEvent::Uncontain => (event, profile_1, profile_2),
+                    _ => new_event(event, profile_1, profile_2),
+                });
             }
         }
         None
     }
 
-    fn process_event(&mut self, event: InternalEvent) -> Option<(HbEvent, HbId, HbId)> {
+    fn process_event(&mut self, event: InternalEvent) -> Option<(HbEvent, HbSlot, HbSlot)> {
         match event {
-            InternalEvent::Collide(id_1, id_2) => {
-                let mut hitbox_info_1 = self.hitboxes.remove(&id_1).unwrap();
-                {
-                    let hitbox_info_2 = self.hitboxes.get_mut(&id_2).unwrap();
+            InternalEvent::Collide(slot_1, slot_2) => {
+                let mut hitbox_info_1 = self.hitboxes.take(slot_1);
+                let solid = {
+                    let hitbox_info_2 = &mut self.hitboxes[slot_2];
                     Collider::process_collision(
-                        id_1,
+                        slot_1,
                         &mut hitbox_info_1,
-                        id_2,
+                        slot_2,
                         hitbox_info_2,
                         &mut self.events,
                         self.time,
                         self.padding,
+                    )
+                };
+                self.hitboxes.put(slot_1, hitbox_info_1);
+                if solid {
+                    Some((HbEvent::Collide, slot_1, slot_2))
+                } else {
+                    None
+                }
+            }
+            InternalEvent::Separate(slot_1, slot_2) => {
+                let mut hitbox_info_1 = self.hitboxes.take(slot_1);
+                let solid = hitbox_info_1.overlaps.remove(&slot_2);
+                {
+                    let hitbox_info_2 = &mut self.hitboxes[slot_2];
+                    if solid {
+                        assert!(hitbox_info_2.overlaps.remove(&slot_1));
+                    } else {
+                        assert!(hitbox_info_1.ignored_overlaps.remove(&slot_2));
+                        assert!(hitbox_info_2.ignored_overlaps.remove(&slot_1));
+                    }
+                    let depth = collide_depth(&hitbox_info_1.profile, &hitbox_info_2.profile);
+                    let delay = hitbox_info_1
+                        .hitbox_at_time(self.time)
+                        .deep_collide_time(&hitbox_info_2.hitbox_at_time(self.time), depth);
+                    self.events.add_pair_event(
+                        self.time + delay,
+                        InternalEvent::Collide(slot_1, slot_2),
+                        &mut hitbox_info_1.event_keys,
+                        &mut hitbox_info_2.event_keys,
                     );
                 }
-                assert!(self.hitboxes.insert(id_1, hitbox_info_1).is_none());
-                Some(new_event(HbEvent::Collide, id_1, id_2))
+                self.hitboxes.put(slot_1, hitbox_info_1);
+                if solid {
+                    Some((HbEvent::Separate, slot_1, slot_2))
+                } else {
+                    None
+                }
             }
-            InternalEvent::Separate(id_1, id_2) => {
-                let mut hitbox_info_1 = self.hitboxes.remove(&id_1).unwrap();
+            InternalEvent::Enter(slot_1, slot_2) => {
+                let mut hitbox_info_1 = self.hitboxes.take(slot_1);
                 {
-                    let hitbox_info_2 = self.hitboxes.get_mut(&id_2).unwrap();
-                    assert!(hitbox_info_1.overlaps.remove(&id_2));
-                    assert!(hitbox_info_2.overlaps.remove(&id_1));
+                    let hitbox_info_2 = &mut self.hitboxes[slot_2];
+                    assert!(hitbox_info_1.proximities.insert(slot_2));
+                    assert!(hitbox_info_2.proximities.insert(slot_1));
+                    let (profile_1, profile_2) = (&hitbox_info_1.profile, &hitbox_info_2.profile);
+                    let margin = pair_margin(profile_1, profile_2)
+                        .expect("proximity between hitboxes that are not sensors");
+                    let padding = pair_padding(self.padding, profile_1, profile_2);
                     let delay = hitbox_info_1
                         .hitbox_at_time(self.time)
+                        .padded(margin)
+                        .separate_time(&hitbox_info_2.hitbox_at_time(self.time), padding);
+                    self.events.add_pair_event(
+                        self.time + delay,
+                        InternalEvent::Exit(slot_1, slot_2),
+                        &mut hitbox_info_1.event_keys,
+                        &mut hitbox_info_2.event_keys,
+                    );
+                }
+                self.hitboxes.put(slot_1, hitbox_info_1);
+                Some((HbEvent::Enter, slot_1, slot_2))
+            }
+            InternalEvent::Exit(slot_1, slot_2) => {
+                let mut hitbox_info_1 = self.hitboxes.take(slot_1);
+                {
+                    let hitbox_info_2 = &mut self.hitboxes[slot_2];
+                    assert!(hitbox_info_1.proximities.remove(&slot_2));
+                    assert!(hitbox_info_2.proximities.remove(&slot_1));
+                    let margin = pair_margin(&hitbox_info_1.profile, &hitbox_info_2.profile)
+                        .expect("proximity between hitboxes that are not sensors");
+                    let delay = hitbox_info_1
+                        .hitbox_at_time(self.time)
+                        .padded(margin)
                         .collide_time(&hitbox_info_2.hitbox_at_time(self.time));
                     self.events.add_pair_event(
                         self.time + delay,
-                        InternalEvent::Collide(id_1, id_2),
+                        InternalEvent::Enter(slot_1, slot_2),
                         &mut hitbox_info_1.event_keys,
                         &mut hitbox_info_2.event_keys,
                     );
                 }
-                assert!(self.hitboxes.insert(id_1, hitbox_info_1).is_none());
-                Some(new_event(HbEvent::Separate, id_1, id_2))
+                self.hitboxes.put(slot_1, hitbox_info_1);
+                Some((HbEvent::Exit, slot_1, slot_2))
             }
-            InternalEvent::Reiterate(id) => {
-                self.internal_update_hitbox(id, None);
+            InternalEvent::Contain(outer_slot, inner_slot) => {
+                let mut outer_info = self.hitboxes.take(outer_slot);
+                {
+                    let inner_info = &mut self.hitboxes[inner_slot];
+                    assert!(outer_info.contents.insert(inner_slot));
+                    assert!(inner_info.containers.insert(outer_slot));
+                    let padding =
+                        pair_padding(self.padding, &outer_info.profile, &inner_info.profile);
+                    let delay = inner_info
+                        .hitbox_at_time(self.time)
+                        .uncontain_time(&outer_info.hitbox_at_time(self.time), padding);
+                    self.events.add_pair_event(
+                        self.time + delay,
+                        InternalEvent::Uncontain(outer_slot, inner_slot),
+                        &mut outer_info.event_keys,
+                        &mut inner_info.event_keys,
+                    );
+                }
+                self.hitboxes.put(outer_slot, outer_info);
+                Some((HbEvent::Contain, outer_slot, inner_slot))
+            }
+            InternalEvent::Uncontain(outer_slot, inner_slot) => {
+                let mut outer_info = self.hitboxes.take(outer_slot);
+                {
+                    let inner_info = &mut self.hitboxes[inner_slot];
+                    assert!(outer_info.contents.remove(&inner_slot));
+                    assert!(inner_info.containers.remove(&outer_slot));
+                    let delay = inner_info
+                        .hitbox_at_time(self.time)
+                        .contain_time(&outer_info.hitbox_at_time(self.time));
+                    self.events.add_pair_event(
+                        self.time + delay,
+                        InternalEvent::Contain(outer_slot, inner_slot),
+                        &mut outer_info.event_keys,
+                        &mut inner_info.event_keys,
+                    );
+                }
+                self.hitboxes.put(outer_slot, outer_info);
+                Some((HbEvent::Uncontain, outer_slot, inner_slot))
+            }
+            InternalEvent::Reiterate(slot) => {
+                // hitboxes that reiterate at the same time are refreshed together
+                let mut batch = Batch::new();
+                self.update_to_batch(slot, None, None, &mut batch);
+                while let Some(slot) = self.events.next_reiterate(self.time, &mut self.hitboxes) {
+                    self.update_to_batch(slot, None, None, &mut batch);
+                }
+                self.track_hitboxes(batch);
+                None
+            }
+            InternalEvent::Waypoint(slot) => {
+                // the hitbox is placed exactly at the waypoint, so that
+                // rounding error does not build up along the path
+                let info = &mut self.hitboxes[slot];
+                let (_, pos) = info.path.pop_front().unwrap();
+                let vel = info.path_vel(pos, self.time);
+                self.move_hitbox(slot, Some(pos), Some(vel));
                 None
             }
+            InternalEvent::OutOfBounds(slot) => {
+                let info = &mut self.hitboxes[slot];
+                info.out_of_bounds = true;
+                self.bounds_events.push_back(info.profile);
+                None
+            }
+            InternalEvent::TileCollide(..) | InternalEvent::TileSeparate(..) => {
+                unreachable!("tile events are processed by next")
+            }
             #[cfg(debug_assertions)]
-            InternalEvent::PanicSmallHitbox(id) => panic!("hitbox {} became too small", id),
+            InternalEvent::PanicSmallHitbox(slot) => panic!(
+                "hitbox {} became too small",
+                self.hitboxes[slot].profile.id()
+            ),
             #[cfg(debug_assertions)]
-            InternalEvent::PanicDurationPassed(id) => {
-                panic!("hitbox {} was not updated before duration passed", id)
+            InternalEvent::PanicDurationPassed(slot) => panic!(
+                "hitbox {} was not updated before duration passed",
+                self.hitboxes[slot].profile.id()
+            ),
+        }
+    }
+
+    fn process_tile_event(&mut self, slot: HbSlot, event: HbEvent) -> (HbEvent, P, P) {
+        let mut info = self.hitboxes.take(slot);
+        info.tile_contact = match event {
+            HbEvent::TileCollide(coord) => Some(coord),
+            _ => None,
+        };
+        let hitbox = info.hitbox_at_time(self.time);
+        self.tile_event_check(slot, &mut info, &hitbox);
+        let profile = info.profile;
+        self.hitboxes.put(slot, info);
+        (event, profile, self.tile_profile.unwrap())
+    }
+
+    /// Sets the static tile layer that hitboxes may collide with, replacing
+    /// any previous layer.
+    ///
+    /// `profile` stands in for the layer in the events returned by
+    /// `self.next()`, so its ID must not be used by any hitbox. Hitboxes that
+    /// were touching the previous layer will be given a `TileSeparate` event
+    /// if they are no longer touching the new layer, and hitboxes that are
+    /// touching the new layer will be given a `TileCollide` event, both at the
+    /// current time.
+    pub fn set_tile_layer(&mut self, tiles: TileLayer, profile: P) {
+        assert!(
+            !self.slots.contains_key(&profile.id()),
+            "hitbox id {} already exists",
+            profile.id()
+        );
+        let slots: Vec<HbSlot> = self
+            .hitboxes
+            .iter()
+            .filter(|(_, info)| info.tile_contact.is_some() || tiles.interacts_with(&info.profile))
+            .map(|(slot, _)| slot)
+            .collect();
+        self.tiles = Some(tiles);
+        self.tile_profile = Some(profile);
+        for slot in slots {
+            self.internal_update_hitbox(slot, None, None);
+        }
+    }
+
+    /// Returns the tile layer, if one has been set.
+    pub fn tile_layer(&self) -> Option<&TileLayer> {
+        self.tiles.as_ref()
+    }
+
+    /// Sets whether the tile at `coord` in the tile layer is solid.
+    ///
+    /// Any resulting `Collide` or `Separate` events will occur at the current
+    /// time. Panics if no tile layer has been set.
+    pub fn set_tile_solid(&mut self, coord: TileCoord, solid: bool) {
+        let tiles = self.tiles.as_mut().expect("no tile layer has been set");
+        if tiles.is_solid(coord) == solid {
+            return;
+        }
+        tiles.set_solid(coord, solid);
+        let tile = tiles.tile_shape(coord);
+        let (time, default_padding) = (self.time, self.padding);
+        let slots: Vec<HbSlot> = self
+            .hitboxes
+            .iter()
+            .filter(|(_, info)| tiles.interacts_with(&info.profile))
+            .filter(|(_, info)| {
+                let padding = info.profile.padding().unwrap_or(default_padding);
+                let bounds = info.hitbox_at_time(time).padded(padding).bounding_box();
+                bounds.overlaps(&tile)
+            })
+            .map(|(slot, _)| slot)
+            .collect();
+        for slot in slots {
+            self.internal_update_hitbox(slot, None, None);
+        }
+    }
+
+    /// Returns the tile that the hitbox with the given `id` most recently
+    /// started touching, or `None` if it is not touching the tile layer.
+    pub fn get_tile_contact(&self, id: HbId) -> Option<TileCoord> {
+        self.hitboxes[self.slot(id)].tile_contact
+    }
+
+    fn bounds_event_check(&mut self, slot: HbSlot, info: &mut HitboxInfo<P>, hitbox: &DurHitbox) {
+        let bounds = match self.bounds {
+            Some(ref bounds) if self.report_out_of_bounds => bounds,
+            _ => return,
+        };
+        let delay = hitbox.exit_time(bounds);
+        if delay > 0.0 {
+            info.out_of_bounds = false;
+        }
+        if !info.out_of_bounds {
+            self.events.add_solitaire_event(
+                self.time + delay,
+                InternalEvent::OutOfBounds(slot),
+                &mut info.event_keys,
+            );
+        }
+    }
+
+    fn tile_event_check(&mut self, slot: HbSlot, info: &mut HitboxInfo<P>, hitbox: &DurHitbox) {
+        let tiles = match self.tiles {
+            Some(ref tiles) if tiles.interacts_with(&info.profile) => tiles,
+            _ => {
+                if let Some(coord) = info.tile_contact {
+                    self.events.add_solitaire_event(
+                        self.time,
+                        InternalEvent::TileSeparate(slot, coord),
+                        &mut info.event_keys,
+                    );
+                }
+                return;
             }
+        };
+        if let Some(contact) = info.tile_contact {
+            let padding = info.profile.padding().unwrap_or(self.padding);
+            let (delay, coord) = tiles.separate_time(hitbox, padding);
+            self.events.add_solitaire_event(
+                self.time + delay,
+                InternalEvent::TileSeparate(slot, coord.unwrap_or(contact)),
+                &mut info.event_keys,
+            );
+        } else if let Some((delay, coord)) = tiles.collide_time(hitbox) {
+            self.events.add_solitaire_event(
+                self.time + delay,
+                InternalEvent::TileCollide(slot, coord),
+                &mut info.event_keys,
+            );
         }
     }
 
+    // returns false if the collision is ignored due to the solid sides of the
+    // hitboxes, in which case it is tracked separately until the hitboxes
+    // separate
     fn process_collision(
-        id_1: HbId,
+        slot_1: HbSlot,
         hb_1: &mut HitboxInfo<P>,
-        id_2: HbId,
+        slot_2: HbSlot,
         hb_2: &mut HitboxInfo<P>,
         events: &mut EventManager,
         time: f64,
         padding: f64,
-    ) {
-        assert!(hb_1.overlaps.insert(id_2));
-        assert!(hb_2.overlaps.insert(id_1));
-        let delay = hb_1
-            .hitbox_at_time(time)
-            .separate_time(&hb_2.hitbox_at_time(time), padding);
+    ) -> bool {
+        let hitbox_1 = hb_1.hitbox_at_time(time);
+        let hitbox_2 = hb_2.hitbox_at_time(time);
+        let padding = pair_padding(padding, &hb_1.profile, &hb_2.profile);
+        let solid = is_solid_collision(hb_1, &hitbox_1, hb_2, &hitbox_2, padding);
+        if solid {
+            assert!(hb_1.overlaps.insert(slot_2));
+            assert!(hb_2.overlaps.insert(slot_1));
+        } else {
+            assert!(hb_1.ignored_overlaps.insert(slot_2));
+            assert!(hb_2.ignored_overlaps.insert(slot_1));
+        }
+        let depth = separate_depth(&hb_1.profile, &hb_2.profile);
+        let delay = hitbox_1.deep_separate_time(&hitbox_2, depth, padding);
         events.add_pair_event(
             time + delay,
-            InternalEvent::Separate(id_1, id_2),
+            InternalEvent::Separate(slot_1, slot_2),
             &mut hb_1.event_keys,
             &mut hb_2.event_keys,
         );
+        solid
     }
 
     /// Returns the current state of the hitbox with the given `id`.
     pub fn get_hitbox(&self, id: HbId) -> Hitbox {
-        self.hitboxes[&id].pub_hitbox_at_time(self.time)
+        self.hitboxes[self.slot(id)].pub_hitbox_at_time(self.time)
+    }
+
+    fn slot(&self, id: HbId) -> HbSlot {
+        *self
+            .slots
+            .get(&id)
+            .unwrap_or_else(|| panic!("hitbox id {} not found", id))
     }
 
     /// # Adds a new hitbox to the collider.
@@ -210,90 +677,452 @@ impl<P: HbProfile> Collider<P> {
     ///
     /// Returns a vector of all hitbox profiles that this new hitbox collided
     /// with as it was added. Note that separate collision events will not be
-    /// generated for these collisions.
+    /// generated for these collisions. If the new hitbox is touching the tile
+    /// layer, then a tile `Collide` event will be generated at the current time
+    /// instead.
     pub fn add_hitbox(&mut self, profile: P, hitbox: Hitbox) -> Vec<P> {
-        hitbox.validate(self.padding, self.time);
+        let mut batch = Batch::new();
+        self.add_to_batch(profile, hitbox, &mut batch);
+        self.track_hitboxes(batch).pop().unwrap()
+    }
+
+    /// Adds many new hitboxes to the collider at once.
+    ///
+    /// This is equivalent to calling `self.add_hitbox` for each profile and
+    /// hitbox in turn, but is more efficient when adding a large number of
+    /// hitboxes, such as when loading a level. Each pair of hitboxes is only
+    /// tested once, and the collision times of all pairs are solved together,
+    /// in parallel if the `parallel` feature is enabled.
+    ///
+    /// Returns a map from the ID of each new hitbox to the profiles of all
+    /// hitboxes that it collided with as it was added, including other new
+    /// hitboxes. As with `self.add_hitbox`, separate collision events will not
+    /// be generated for these collisions.
+    pub fn add_hitboxes<I>(&mut self, hitboxes: I) -> HashMap<HbId, Vec<P>>
+    where
+        I: IntoIterator<Item = (P, Hitbox)>,
+    {
+        let mut batch = Batch::new();
+        let ids: Vec<HbId> = hitboxes
+            .into_iter()
+            .map(|(profile, hitbox)| {
+                self.add_to_batch(profile, hitbox, &mut batch);
+                profile.id()
+            })
+            .collect();
+        ids.into_iter().zip(self.track_hitboxes(batch)).collect()
+    }
+
+    fn add_to_batch(&mut self, profile: P, hitbox: Hitbox, batch: &mut Batch) {
+        let padding = profile.padding().unwrap_or(self.padding);
+        assert!(padding > 0.0, "hitbox padding must be positive");
+        hitbox.validate(padding, self.time);
+        assert!(
+            profile.solid_sides() == CardMask::full() || hitbox.value.kind() == ShapeKind::Rect,
+            "only rect hitboxes may have solid sides other than full"
+        );
+        if let Some(margin) = profile.sensor_margin() {
+            assert!(margin > 0.0, "sensor margin must be positive");
+        }
         let id = profile.id();
+        assert!(
+            !self.slots.contains_key(&id) && self.tile_profile.map(|tile| tile.id()) != Some(id),
+            "hitbox id {} already exists",
+            id
+        );
+        let slot = self.hitboxes.reserve();
+        self.slots.insert(id, slot);
         let has_group = profile.group().is_some();
         let mut info = HitboxInfo::new(hitbox, profile, self.time);
-        self.solitaire_event_check(id, &mut info, has_group);
+        self.solitaire_event_check(slot, &mut info, has_group);
         let dur_hitbox = info.hitbox.to_dur_hitbox(self.time);
-        self.update_hitbox_tracking(id, info, None, dur_hitbox)
+        self.hitboxes.put(slot, info);
+        batch.push(slot, None, dur_hitbox);
+    }
+
+    /// Merges the given axis-aligned rectangles with `geom::merge_rects` and
+    /// adds each of the resulting rectangles as a stationary hitbox.
+    ///
+    /// `profile_fn` is invoked with each merged rectangle to obtain the
+    /// profile of its hitbox. Using fewer, larger static hitboxes reduces the
+    /// cost of tracking them, and prevents `Separate` and `Collide` events from
+    /// occurring as a hitbox slides across the shared edge of two rectangles.
+    ///
+    /// Returns the profiles of the added hitboxes. Any overlaps with existing
+    /// hitboxes are tracked as usual but are not returned.
+    pub fn add_static_rects<F>(&mut self, rects: &[PlacedShape], mut profile_fn: F) -> Vec<P>
+    where
+        F: FnMut(&PlacedShape) -> P,
+    {
+        geom::merge_rects(rects)
+            .into_iter()
+            .map(|rect| {
+                let profile = profile_fn(&rect);
+                self.add_hitbox(profile, rect.still());
+                profile
+            })
+            .collect()
     }
 
     /// Updates the velocity information of the hitbox with the given `id`.
+    ///
+    /// This stops the hitbox from following any path that was set with
+    /// `self.set_hitbox_path`. Any hitboxes attached to it are given the same
+    /// velocity at once. Panics if the hitbox is itself attached to a parent.
     pub fn set_hitbox_vel(&mut self, id: HbId, vel: HbVel) {
-        if self.hitboxes[&id].hitbox.vel != vel {
-            self.internal_update_hitbox(id, Some(vel));
+        let slot = self.unattached_slot(id);
+        let info = &mut self.hitboxes[slot];
+        if !info.path.is_empty() || info.hitbox.vel != vel {
+            info.path.clear();
+            self.move_hitbox(slot, None, Some(vel));
         }
     }
 
-    fn internal_update_hitbox(&mut self, id: HbId, vel: Option<HbVel>) {
-        let mut info = self
-            .hitboxes
-            .remove(&id)
-            .unwrap_or_else(|| panic!("hitbox id {} not found", id));
+    // Moves the hitbox so that it is centered at `pos` without sweeping
+    // through the space in between, for the push-outs of the `response` and
+    // `controller` modules. Any resulting collisions or separations are
+    // reported at the current time. A path is stopped, leaving the hitbox
+    // still, and attached hitboxes are moved along with it.
+    pub(crate) fn set_hitbox_pos(&mut self, id: HbId, pos: Vec2) {
+        let slot = self.unattached_slot(id);
+        let info = &mut self.hitboxes[slot];
+        if !info.path.is_empty() {
+            info.path.clear();
+            let vel = info.path_vel(pos, self.time);
+            self.move_hitbox(slot, Some(pos), Some(vel));
+        } else if info.pub_hitbox_at_time(self.time).value.pos != pos {
+            self.move_hitbox(slot, Some(pos), None);
+        }
+    }
+
+    /// Makes the hitbox with the given `id` follow a path through the given
+    /// `waypoints`, each of which is a time and the position that the hitbox
+    /// reaches at that time.
+    ///
+    /// The hitbox moves in a straight line from its current position to each
+    /// waypoint in turn, at the constant velocity that reaches it on time, and
+    /// stays still after the last one. Its resize and angular velocities are
+    /// kept, but it stops orbiting. The velocity is switched by the collider
+    /// at each waypoint, so the user does not need to call
+    /// `self.set_hitbox_vel`, and the times of events remain exact across
+    /// waypoints. The times of the waypoints must be increasing and after the
+    /// current time. Any previous path is replaced, and setting the velocity
+    /// of the hitbox stops it from following the path. Hitboxes attached to
+    /// it follow along, but a hitbox that is attached to a parent may not
+    /// follow a path of its own.
+    pub fn set_hitbox_path(&mut self, id: HbId, waypoints: &[(f64, Vec2)]) {
+        let slot = self.unattached_slot(id);
+        let mut prev_time = self.time;
+        for &(time, pos) in waypoints {
+            assert!(
+                time > prev_time && time < HIGH_TIME,
+                "waypoint times must be increasing and after the current time"
+            );
+            assert!(
+                pos.x.is_finite() && pos.y.is_finite(),
+                "waypoint positions must be finite"
+            );
+            prev_time = time;
+        }
+        let info = &mut self.hitboxes[slot];
+        info.path = waypoints.iter().cloned().collect();
+        let pos = info.pub_hitbox_at_time(self.time).value.pos;
+        let vel = info.path_vel(pos, self.time);
+        self.move_hitbox(slot, None, Some(vel));
+    }
+
+    /// Attaches the hitbox with the given `child` ID to the hitbox with the
+    /// given `parent` ID, so that it stays at `offset` from the position of
+    /// the parent.
+    ///
+    /// The child is moved to its offset at once, and its movement velocity,
+    /// orbit and end time are kept the same as those of the parent from then
+    /// on, while its resize and angular velocities remain its own. An orbiting
+    /// child moves around a center at the same offset, so it stays at its
+    /// offset from the parent. Whenever the velocity of the parent is set, or
+    /// the parent reaches a waypoint of its path, the child is updated along
+    /// with it, and the events of both are solved together. The velocity of
+    /// the child may not be set directly while it is attached. A child may
+    /// have children of its own, but hitboxes may not be attached in a cycle.
+    /// If the child was already attached to a parent, it is detached from it
+    /// first.
+    pub fn attach_hitbox(&mut self, child: HbId, parent: HbId, offset: Vec2) {
+        let (child_slot, parent_slot) = (self.slot(child), self.slot(parent));
+        let mut ancestor = Some(parent_slot);
+        while let Some(slot) = ancestor {
+            assert!(
+                slot != child_slot,
+                "hitboxes may not be attached in a cycle"
+            );
+            ancestor = self.hitboxes[slot].parent.map(|(slot, _)| slot);
+        }
+        self.unlink_parent(child_slot);
+        self.hitboxes[parent_slot].children.push(child_slot);
+        let info = &mut self.hitboxes[child_slot];
+        info.path.clear();
+        info.parent = Some((parent_slot, offset));
+        let (pos, vel) = self.attached_state(child_slot);
+        self.move_hitbox(child_slot, Some(pos), Some(vel));
+    }
+
+    /// Detaches the hitbox with the given `id` from its parent, if it has one.
+    ///
+    /// The hitbox keeps its current velocity, which may then be set directly.
+    pub fn detach_hitbox(&mut self, id: HbId) {
+        let slot = self.slot(id);
+        self.unlink_parent(slot);
+    }
+
+    /// Returns the profile of the parent that the hitbox with the given `id`
+    /// is attached to, if any.
+    pub fn get_parent(&self, id: HbId) -> Option<P> {
+        self.hitboxes[self.slot(id)]
+            .parent
+            .map(|(parent_slot, _)| self.hitboxes[parent_slot].profile)
+    }
+
+    fn unattached_slot(&self, id: HbId) -> HbSlot {
+        let slot = self.slot(id);
+        assert!(
+            self.hitboxes[slot].parent.is_none(),
+            "hitbox {} is attached to a parent",
+            id
+        );
+        slot
+    }
+
+    fn unlink_parent(&mut self, slot: HbSlot) {
+        if let Some((parent_slot, _)) = self.hitboxes[slot].parent.take() {
+            let children = &mut self.hitboxes[parent_slot].children;
+            children.retain(|&child_slot| child_slot != slot);
+        }
+    }
+
+    // returns the position and velocity that an attached hitbox must have to
+    // stay at its offset from its parent
+    fn attached_state(&self, slot: HbSlot) -> (Vec2, HbVel) {
+        let info = &self.hitboxes[slot];
+        let (parent_slot, offset) = info.parent.unwrap();
+        let parent = self.hitboxes[parent_slot].pub_hitbox_at_time(self.time);
+        // an orbit around a center shifted by the offset keeps the offset
+        let orbit = parent.vel.orbit.map(|orbit| Orbit {
+            center: orbit.center + offset,
+            ..orbit
+        });
+        let vel = HbVel {
+            value: parent.vel.value,
+            orbit,
+            end_time: parent.vel.end_time,
+            ..info.hitbox.vel.clone()
+        };
+        (parent.value.pos + offset, vel)
+    }
+
+    /// Updates the velocity information of many hitboxes at once.
+    ///
+    /// This is equivalent to calling `self.set_hitbox_vel` for each ID and
+    /// velocity in turn, but the collision times of all of the affected pairs
+    /// of hitboxes are solved together, in parallel if the `parallel` feature
+    /// is enabled. If an ID occurs more than once, its last velocity is used.
+    pub fn set_hitbox_vels<I>(&mut self, vels: I)
+    where
+        I: IntoIterator<Item = (HbId, HbVel)>,
+    {
+        let mut batch = Batch::new();
+        for (id, vel) in vels {
+            let slot = self.unattached_slot(id);
+            let info = &mut self.hitboxes[slot];
+            if !info.path.is_empty() || info.hitbox.vel != vel {
+                info.path.clear();
+                self.family_to_batch(slot, None, Some(vel), &mut batch);
+            }
+        }
+        let result = self.track_hitboxes(batch);
+        assert!(result.iter().all(Vec::is_empty));
+    }
+
+    // updates the hitbox along with every hitbox attached to it
+    fn move_hitbox(&mut self, slot: HbSlot, pos: Option<Vec2>, vel: Option<HbVel>) {
+        let mut batch = Batch::new();
+        self.family_to_batch(slot, pos, vel, &mut batch);
+        let result = self.track_hitboxes(batch);
+        assert!(result.iter().all(Vec::is_empty));
+    }
+
+    fn family_to_batch(
+        &mut self,
+        slot: HbSlot,
+        pos: Option<Vec2>,
+        vel: Option<HbVel>,
+        batch: &mut Batch,
+    ) {
+        self.update_to_batch(slot, pos, vel, batch);
+        for child_slot in self.hitboxes[slot].children.clone() {
+            let (pos, vel) = self.attached_state(child_slot);
+            self.family_to_batch(child_slot, Some(pos), Some(vel), batch);
+        }
+    }
+
+    fn internal_update_hitbox(&mut self, slot: HbSlot, pos: Option<Vec2>, vel: Option<HbVel>) {
+        let mut batch = Batch::new();
+        self.update_to_batch(slot, pos, vel, &mut batch);
+        let result = self.track_hitboxes(batch);
+        assert!(result[0].is_empty());
+    }
+
+    fn update_to_batch(
+        &mut self,
+        slot: HbSlot,
+        pos: Option<Vec2>,
+        vel: Option<HbVel>,
+        batch: &mut Batch,
+    ) {
+        let mut info = self.hitboxes.take(slot);
         let old_hitbox = info.hitbox.to_dur_hitbox(info.start_time);
         info.hitbox = info.pub_hitbox_at_time(self.time);
+        if let Some(pos) = pos {
+            info.hitbox.value.pos = pos;
+        }
         if let Some(vel) = vel {
             info.hitbox.vel = vel;
-            info.hitbox.validate(self.padding, self.time);
+            let padding = info.profile.padding().unwrap_or(self.padding);
+            info.hitbox.validate(padding, self.time);
         }
         info.start_time = self.time;
         let has_group = info.profile.group().is_some();
         self.events
-            .clear_related_events(id, &mut info.event_keys, &mut self.hitboxes);
-        self.solitaire_event_check(id, &mut info, has_group);
+            .clear_related_events(slot, &mut info.event_keys, &mut self.hitboxes);
+        self.solitaire_event_check(slot, &mut info, has_group);
         let new_hitbox = info.hitbox.to_dur_hitbox(self.time);
-        let result = self.update_hitbox_tracking(id, info, Some(old_hitbox), new_hitbox);
-        assert!(result.is_empty());
+        self.hitboxes.put(slot, info);
+        batch.push(slot, Some(old_hitbox), new_hitbox);
     }
 
     /// Removes the hitbox with the given `id` from all tracking.
     ///
     /// Returns a vector of all hitbox profiles that this hitbox separated from
-    /// as it was removed. No further events will be generated for this hitbox.
+    /// as it was removed. No further events will be generated for this hitbox,
+    /// including tile events and the `Exit` and `Uncontain` events of pairs
+    /// that it was in. Any hitboxes attached to it are detached, keeping their
+    /// current velocities.
     pub fn remove_hitbox(&mut self, id: HbId) -> Vec<P> {
-        let mut info = self
-            .hitboxes
+        let slot = self
+            .slots
             .remove(&id)
             .unwrap_or_else(|| panic!("hitbox id {} not found", id));
+        self.unlink_parent(slot);
+        for child_slot in mem::take(&mut self.hitboxes[slot].children) {
+            self.hitboxes[child_slot].parent = None;
+        }
+        let mut info = self.hitboxes.take(slot);
         self.events
-            .clear_related_events(id, &mut info.event_keys, &mut self.hitboxes);
+            .clear_related_events(slot, &mut info.event_keys, &mut self.hitboxes);
         if let Some(group) = info.profile.group() {
             let info_start_time = info.start_time;
             let empty_group_array: &[HbGroup] = &[];
             self.grid.update_hitbox(
-                id,
+                slot,
                 group,
-                Some(&info.hitbox.to_dur_hitbox(info_start_time)),
+                Some(&info.grid_hitbox(&info.hitbox.to_dur_hitbox(info_start_time))),
                 None,
                 empty_group_array,
             );
         }
-        self.clear_overlaps(id, &mut info)
+        let result = self.clear_overlaps(slot, &mut info);
+        self.hitboxes.free(slot);
+        result
+    }
+
+    /// Removes many hitboxes from the collider at once.
+    ///
+    /// This is equivalent to calling `self.remove_hitbox` for each ID in turn.
+    /// Returns a map from each removed ID to the profiles of all hitboxes that
+    /// it separated from as it was removed, including other removed hitboxes.
+    pub fn remove_hitboxes<I>(&mut self, ids: I) -> HashMap<HbId, Vec<P>>
+    where
+        I: IntoIterator<Item = HbId>,
+    {
+        let ids: Vec<HbId> = ids.into_iter().collect();
+        let mut result: HashMap<HbId, Vec<P>> = ids.iter().map(|&id| (id, Vec::new())).collect();
+        for id in ids {
+            let profile = self.hitboxes[self.slot(id)].profile;
+            let separated = self.remove_hitbox(id);
+            for other_profile in &separated {
+                if let Some(overlaps) = result.get_mut(&other_profile.id()) {
+                    overlaps.push(profile);
+                }
+            }
+            result.get_mut(&id).unwrap().extend(separated);
+        }
+        result
     }
 
     /// Returns the profiles of all currently tracked overlaps on the hitbox
     /// with the given `id`.
     pub fn get_overlaps(&self, id: HbId) -> Vec<P> {
-        let info = self
-            .hitboxes
-            .get(&id)
-            .unwrap_or_else(|| panic!("hitbox id {} not found", id));
-        info.overlaps
+        self.hitboxes[self.slot(id)]
+            .overlaps
             .iter()
-            .map(|other_id| self.hitboxes[other_id].profile)
+            .map(|&other_slot| self.hitboxes[other_slot].profile)
             .collect()
     }
 
     /// Returns true if there is a currently tracked overlap between the
     /// hitboxes with `id_1` and `id_2`.
     pub fn is_overlapping(&self, id_1: HbId, id_2: HbId) -> bool {
-        self.hitboxes
-            .get(&id_1)
-            .map(|info| info.overlaps.contains(&id_2))
-            .unwrap_or(false)
+        match (self.slots.get(&id_1), self.slots.get(&id_2)) {
+            (Some(&slot_1), Some(&slot_2)) => self.hitboxes[slot_1].overlaps.contains(&slot_2),
+            _ => false,
+        }
+    }
+
+    /// Returns the earliest pending `Collide` or `Separate` event involving the
+    /// hitbox with the given `id`, as the time of the event, its type, and the
+    /// profile of the other hitbox, or `None` if there is none.
+    ///
+    /// This assumes that no velocities change before then. Events are only
+    /// solved in advance between hitboxes that are near each other in the
+    /// grid, and only until one of them moves on to new grid cells, so a
+    /// later event or one with a hitbox that is still far away is not known
+    /// yet, and may occur before the returned event. A `Collide` that will be
+    /// ignored due to `HbProfile::solid_sides` is skipped, as `self.next()`
+    /// will not report it.
+    pub fn next_event_for(&self, id: HbId) -> Option<(f64, HbEvent, P)> {
+        let slot = self.slot(id);
+        let info = &self.hitboxes[slot];
+        info.event_keys
+            .iter()
+            .filter_map(|key| {
+                let internal_event = self.events.get(key);
+                let event = match internal_event {
+                    InternalEvent::Collide(..) => HbEvent::Collide,
+                    InternalEvent::Separate(..) => HbEvent::Separate,
+                    _ => return None,
+                };
+                let other_slot = internal_event.other_slot(slot).unwrap();
+                let other_info = &self.hitboxes[other_slot];
+                let reported = match event {
+                    // ignored overlaps separate silently
+                    HbEvent::Separate => info.overlaps.contains(&other_slot),
+                    _ => {
+                        let padding =
+                            pair_padding(self.padding, &info.profile, &other_info.profile);
+                        is_solid_collision(
+                            info,
+                            &info.hitbox_at_time(key.time()),
+                            other_info,
+                            &other_info.hitbox_at_time(key.time()),
+                            padding,
+                        )
+                    }
+                };
+                if !reported {
+                    return None;
+                }
+                Some((*key, event, other_slot))
+            })
+            .min_by_key(|&(key, ..)| key)
+            .map(|(key, event, other_slot)| (key.time(), event, self.hitboxes[other_slot].profile))
     }
 
     /// Returns the profiles of all hitboxes that overlap the given `shape` and
@@ -302,83 +1131,226 @@ impl<P: HbProfile> Collider<P> {
         self.grid
             .shape_cellmates(shape, profile.interact_groups())
             .iter()
-            .map(|id| &self.hitboxes[id])
+            .map(|&slot| &self.hitboxes[slot])
             .filter(|info| info.profile.can_interact(profile))
             .filter(|info| info.pub_hitbox_at_time(self.time).value.overlaps(shape))
             .map(|info| info.profile)
             .collect()
     }
 
-    fn update_hitbox_tracking(
-        &mut self,
-        id: HbId,
-        mut info: HitboxInfo<P>,
-        old_hitbox: Option<DurHitbox>,
-        new_hitbox: DurHitbox,
-    ) -> Vec<P> {
-        let mut result = Vec::new();
-        if let Some(group) = info.profile.group() {
-            for &other_id in info.overlaps.clone().iter() {
-                let other_info = self.hitboxes.get_mut(&other_id).unwrap();
-                let delay =
-                    new_hitbox.separate_time(&other_info.hitbox_at_time(self.time), self.padding);
+    // refreshes the tracking of a batch of hitboxes that were just added or
+    // updated, returning the solid overlaps of each hitbox that was added
+    //
+    // Candidate pairs are gathered first, then the times of their next
+    // events are solved (possibly in parallel), and finally the events are
+    // committed in the order the pairs were gathered, so the results do not
+    // depend on how the solving was scheduled.
+    fn track_hitboxes(&mut self, batch: Batch) -> Vec<Vec<P>> {
+        // the buffers are reused, since this runs for every hitbox update
+        let mut jobs = mem::take(&mut self.pair_jobs);
+        let mut cellmates = mem::take(&mut self.cellmates);
+        for (index, (slot, old_hitbox, new_hitbox)) in batch.updates.iter().enumerate() {
+            let info = &self.hitboxes[*slot];
+            let group = match info.profile.group() {
+                Some(group) => group,
+                None => continue,
+            };
+            // pairs within the batch are tracked by whichever comes later
+            let later = |other_slot: HbSlot| match batch.index(other_slot) {
+                Some(other_index) => other_index > index,
+                None => false,
+            };
+            let padding_with = |other_slot: HbSlot| {
+                let other_profile = &self.hitboxes[other_slot].profile;
+                pair_padding(self.padding, &info.profile, other_profile)
+            };
+            for &other_slot in info.overlaps.iter().chain(info.ignored_overlaps.iter()) {
+                if !later(other_slot) {
+                    let depth = separate_depth(&info.profile, &self.hitboxes[other_slot].profile);
+                    let padding = padding_with(other_slot);
+                    jobs.push((index, other_slot, PairKind::Separate { depth, padding }));
+                }
+            }
+            for &other_slot in info.proximities.iter() {
+                if !later(other_slot) {
+                    let other_profile = &self.hitboxes[other_slot].profile;
+                    let margin = pair_margin(&info.profile, other_profile).unwrap();
+                    let padding = padding_with(other_slot);
+                    jobs.push((index, other_slot, PairKind::Exit { margin, padding }));
+                }
+            }
+            for (outer, others) in [(false, &info.containers), (true, &info.contents)] {
+                for &other_slot in others.iter() {
+                    if !later(other_slot) {
+                        let padding = padding_with(other_slot);
+                        jobs.push((index, other_slot, PairKind::Uncontain { outer, padding }));
+                    }
+                }
+            }
+            // sensors are filed in the grid with their margin, so that the
+            // hitboxes near them are found as cellmates
+            let old_hitbox = old_hitbox.as_ref().map(|hitbox| info.grid_hitbox(hitbox));
+            let grid_hitbox = info.grid_hitbox(new_hitbox);
+            self.grid
+                .update_hitbox(*slot, group, old_hitbox.as_deref(), Some(&grid_hitbox), &[]);
+            cellmates.clear();
+            let groups = info.profile.interact_groups();
+            self.grid
+                .fill_cellmates(*slot, &grid_hitbox, groups, &mut cellmates);
+            for &other_slot in cellmates.iter() {
+                let other_profile = &self.hitboxes[other_slot].profile;
+                if later(other_slot) || !info.profile.can_interact(other_profile) {
+                    continue;
+                }
+                if !(info.overlaps.contains(&other_slot)
+                    || info.ignored_overlaps.contains(&other_slot))
+                {
+                    let kind = PairKind::Collide {
+                        added: old_hitbox.is_none(),
+                        depth: collide_depth(&info.profile, other_profile),
+                    };
+                    jobs.push((index, other_slot, kind));
+                }
+                if let Some(margin) = pair_margin(&info.profile, other_profile) {
+                    if !info.proximities.contains(&other_slot) {
+                        jobs.push((index, other_slot, PairKind::Enter { margin }));
+                    }
+                }
+                if other_profile.is_container() && !info.containers.contains(&other_slot) {
+                    jobs.push((index, other_slot, PairKind::Contain { outer: false }));
+                }
+                if info.profile.is_container() && !info.contents.contains(&other_slot) {
+                    jobs.push((index, other_slot, PairKind::Contain { outer: true }));
+                }
+            }
+        }
+
+        let delays = self.pair_delays(&batch, &jobs);
+
+        let mut result = vec![Vec::new(); batch.updates.len()];
+        let mut pairs = jobs.drain(..).zip(delays).peekable();
+        for (index, (slot, _, new_hitbox)) in batch.updates.iter().enumerate() {
+            let mut info = self.hitboxes.take(*slot);
+            while let Some(((_, other_slot, kind), delay)) =
+                pairs.next_if(|((job_index, ..), _)| *job_index == index)
+            {
+                let other_info = &mut self.hitboxes[other_slot];
+                let event = match kind {
+                    PairKind::Separate { .. } => InternalEvent::Separate(*slot, other_slot),
+                    PairKind::Collide { added: true, .. } if delay == 0.0 => {
+                        let solid = Collider::process_collision(
+                            *slot,
+                            &mut info,
+                            other_slot,
+                            other_info,
+                            &mut self.events,
+                            self.time,
+                            self.padding,
+                        );
+                        if solid {
+                            result[index].push(other_info.profile);
+                            if let Some(other_index) = batch.index(other_slot) {
+                                result[other_index].push(info.profile);
+                            }
+                        }
+                        continue;
+                    }
+                    PairKind::Collide { .. } => InternalEvent::Collide(*slot, other_slot),
+                    PairKind::Enter { .. } => InternalEvent::Enter(*slot, other_slot),
+                    PairKind::Exit { .. } => InternalEvent::Exit(*slot, other_slot),
+                    PairKind::Contain { outer: true } => InternalEvent::Contain(*slot, other_slot),
+                    PairKind::Contain { outer: false } => InternalEvent::Contain(other_slot, *slot),
+                    PairKind::Uncontain { outer: true, .. } => {
+                        InternalEvent::Uncontain(*slot, other_slot)
+                    }
+                    PairKind::Uncontain { outer: false, .. } => {
+                        InternalEvent::Uncontain(other_slot, *slot)
+                    }
+                };
                 self.events.add_pair_event(
                     self.time + delay,
-                    InternalEvent::Separate(id, other_id),
+                    event,
                     &mut info.event_keys,
                     &mut other_info.event_keys,
                 );
             }
-            let test_ids = self
-                .grid
-                .update_hitbox(
-                    id,
-                    group,
-                    old_hitbox.as_ref(),
-                    Some(&new_hitbox),
-                    info.profile.interact_groups(),
-                )
-                .unwrap();
-            for other_id in test_ids {
-                if old_hitbox.is_none() || !info.overlaps.contains(&other_id) {
-                    let other_info = self.hitboxes.get_mut(&other_id).unwrap();
-                    if info.profile.can_interact(&other_info.profile) {
-                        let delay = new_hitbox.collide_time(&other_info.hitbox_at_time(self.time));
-                        if old_hitbox.is_none() && delay == 0.0 {
-                            result.push(other_info.profile);
-                            Collider::process_collision(
-                                id,
-                                &mut info,
-                                other_id,
-                                other_info,
-                                &mut self.events,
-                                self.time,
-                                self.padding,
-                            );
-                        } else {
-                            self.events.add_pair_event(
-                                self.time + delay,
-                                InternalEvent::Collide(id, other_id),
-                                &mut info.event_keys,
-                                &mut other_info.event_keys,
-                            );
-                        }
-                    }
-                }
+            // half-planes never move, so they cannot meet tiles or leave the bounds
+            if info.profile.group().is_some() && new_hitbox.value.kind() != ShapeKind::HalfPlane {
+                self.tile_event_check(*slot, &mut info, new_hitbox);
+                self.bounds_event_check(*slot, &mut info, new_hitbox);
             }
+            self.hitboxes.put(*slot, info);
         }
-
-        assert!(self.hitboxes.insert(id, info).is_none());
+        drop(pairs);
+        self.pair_jobs = jobs;
+        self.cellmates = cellmates;
         result
     }
 
-    fn clear_overlaps(&mut self, id: HbId, hitbox_info: &mut HitboxInfo<P>) -> Vec<P> {
+    // solves the time until the next event of each pair of hitboxes in `jobs`
+    fn pair_delays(&self, batch: &Batch, jobs: &[PairJob]) -> Vec<f64> {
+        let updates = &batch.updates;
+        let delay = |&(index, _, kind): &PairJob, other_hitbox: &DurHitbox| {
+            let hitbox = &updates[index].2;
+            match kind {
+                PairKind::Separate { depth, padding } => {
+                    hitbox.deep_separate_time(other_hitbox, depth, padding)
+                }
+                PairKind::Collide { depth, .. } => hitbox.deep_collide_time(other_hitbox, depth),
+                PairKind::Enter { margin } => hitbox.padded(margin).collide_time(other_hitbox),
+                PairKind::Exit { margin, padding } => {
+                    hitbox.padded(margin).separate_time(other_hitbox, padding)
+                }
+                PairKind::Contain { outer: true } => other_hitbox.contain_time(hitbox),
+                PairKind::Contain { outer: false } => hitbox.contain_time(other_hitbox),
+                PairKind::Uncontain {
+                    outer: true,
+                    padding,
+                } => other_hitbox.uncontain_time(hitbox, padding),
+                PairKind::Uncontain {
+                    outer: false,
+                    padding,
+                } => hitbox.uncontain_time(other_hitbox, padding),
+            }
+        };
+        if util::use_parallel(jobs.len()) {
+            // the other hitboxes are copied out so that the profiles do not
+            // need to be shared between threads
+            let pairs: Vec<(PairJob, DurHitbox)> = jobs
+                .iter()
+                .map(|&job| (job, self.hitboxes[job.1].hitbox_at_time(self.time)))
+                .collect();
+            util::par_map(&pairs, |(job, other_hitbox)| delay(job, other_hitbox))
+        } else {
+            jobs.iter()
+                .map(|job| delay(job, &self.hitboxes[job.1].hitbox_at_time(self.time)))
+                .collect()
+        }
+    }
+
+    fn clear_overlaps(&mut self, slot: HbSlot, hitbox_info: &mut HitboxInfo<P>) -> Vec<P> {
+        for other_slot in hitbox_info.proximities.drain() {
+            let other_hitbox_info = &mut self.hitboxes[other_slot];
+            assert!(other_hitbox_info.proximities.remove(&slot));
+        }
+        for other_slot in hitbox_info.containers.drain() {
+            let other_hitbox_info = &mut self.hitboxes[other_slot];
+            assert!(other_hitbox_info.contents.remove(&slot));
+        }
+        for other_slot in hitbox_info.contents.drain() {
+            let other_hitbox_info = &mut self.hitboxes[other_slot];
+            assert!(other_hitbox_info.containers.remove(&slot));
+        }
+        for other_slot in hitbox_info.ignored_overlaps.drain() {
+            let other_hitbox_info = &mut self.hitboxes[other_slot];
+            assert!(other_hitbox_info.ignored_overlaps.remove(&slot));
+        }
         hitbox_info
             .overlaps
             .drain()
-            .map(|other_id| {
-                let other_hitbox_info = self.hitboxes.get_mut(&other_id).unwrap();
-                assert!(other_hitbox_info.overlaps.remove(&id));
+            .map(|other_slot| {
+                let other_hitbox_info = &mut self.hitboxes[other_slot];
+                assert!(other_hitbox_info.overlaps.remove(&slot));
                 other_hitbox_info.profile
             })
             .collect()
@@ -387,22 +1359,32 @@ impl<P: HbProfile> Collider<P> {
     #[cfg(debug_assertions)]
     fn solitaire_event_check(
         &mut self,
-        id: HbId,
+        slot: HbSlot,
         hitbox_info: &mut HitboxInfo<P>,
         has_group: bool,
     ) {
         hitbox_info.pub_end_time = hitbox_info.hitbox.vel.end_time;
+        let grid_hitbox = match hitbox_info.profile.sensor_margin() {
+            Some(margin) => Cow::Owned(hitbox_info.hitbox.padded(margin)),
+            None => Cow::Borrowed(&hitbox_info.hitbox),
+        };
         let mut result = (
-            self.time + self.grid.cell_period(&hitbox_info.hitbox, has_group),
-            InternalEvent::Reiterate(id),
+            self.time + self.grid.cell_period(&grid_hitbox, has_group),
+            InternalEvent::Reiterate(slot),
         );
+        if let Some(&(time, _)) = hitbox_info.path.front() {
+            if time <= result.0 {
+                result = (time, InternalEvent::Waypoint(slot));
+            }
+        }
         let end_time = hitbox_info.hitbox.vel.end_time;
         if end_time < result.0 {
-            result = (end_time, InternalEvent::PanicDurationPassed(id));
+            result = (end_time, InternalEvent::PanicDurationPassed(slot));
         }
-        let end_time = self.time + hitbox_info.hitbox.time_until_too_small(self.padding);
+        let padding = hitbox_info.profile.padding().unwrap_or(self.padding);
+        let end_time = self.time + hitbox_info.hitbox.time_until_too_small(padding);
         if end_time < result.0 {
-            result = (end_time, InternalEvent::PanicSmallHitbox(id));
+            result = (end_time, InternalEvent::PanicSmallHitbox(slot));
         }
         hitbox_info.hitbox.vel.end_time = result.0;
         self.events
@@ -412,37 +1394,44 @@ impl<P: HbProfile> Collider<P> {
     #[cfg(not(debug_assertions))]
     fn solitaire_event_check(
         &mut self,
-        id: HbId,
+        slot: HbSlot,
         hitbox_info: &mut HitboxInfo<P>,
         has_group: bool,
     ) {
         hitbox_info.pub_end_time = hitbox_info.hitbox.vel.end_time;
+        let grid_hitbox = match hitbox_info.profile.sensor_margin() {
+            Some(margin) => Cow::Owned(hitbox_info.hitbox.padded(margin)),
+            None => Cow::Borrowed(&hitbox_info.hitbox),
+        };
         let mut result = (
-            self.time + self.grid.cell_period(&hitbox_info.hitbox, has_group),
-            true,
+            self.time + self.grid.cell_period(&grid_hitbox, has_group),
+            Some(InternalEvent::Reiterate(slot)),
         );
+        if let Some(&(time, _)) = hitbox_info.path.front() {
+            if time <= result.0 {
+                result = (time, Some(InternalEvent::Waypoint(slot)));
+            }
+        }
         let end_time = hitbox_info.hitbox.vel.end_time;
         if end_time < result.0 {
-            result = (end_time, false);
+            result = (end_time, None);
         }
-        let end_time = self.time + hitbox_info.hitbox.time_until_too_small(self.padding);
+        let padding = hitbox_info.profile.padding().unwrap_or(self.padding);
+        let end_time = self.time + hitbox_info.hitbox.time_until_too_small(padding);
         if end_time < result.0 {
-            result = (end_time, false);
+            result = (end_time, None);
         }
         hitbox_info.hitbox.vel.end_time = result.0;
-        if result.1 {
-            self.events.add_solitaire_event(
-                result.0,
-                InternalEvent::Reiterate(id),
-                &mut hitbox_info.event_keys,
-            );
+        if let Some(event) = result.1 {
+            self.events
+                .add_solitaire_event(result.0, event, &mut hitbox_info.event_keys);
         }
     }
 }
 
-impl<P: HbProfile> EventKeysMap for FnvHashMap<HbId, HitboxInfo<P>> {
-    fn event_keys_mut(&mut self, id: HbId) -> &mut TightSet<EventKey> {
-        &mut self.get_mut(&id).unwrap().event_keys
+impl<P: HbProfile> EventKeysMap for Slab<HitboxInfo<P>> {
+    fn event_keys_mut(&mut self, slot: HbSlot) -> &mut TightSet<EventKey> {
+        &mut self[slot].event_keys
     }
 }
 
@@ -452,7 +1441,16 @@ struct HitboxInfo<P: HbProfile> {
     start_time: f64,
     pub_end_time: f64,
     event_keys: TightSet<EventKey>,
-    overlaps: TightSet<HbId>,
+    overlaps: TightSet<HbSlot>,
+    ignored_overlaps: TightSet<HbSlot>,
+    proximities: TightSet<HbSlot>,
+    containers: TightSet<HbSlot>,
+    contents: TightSet<HbSlot>,
+    tile_contact: Option<TileCoord>,
+    out_of_bounds: bool,
+    path: VecDeque<(f64, Vec2)>,
+    parent: Option<(HbSlot, Vec2)>,
+    children: Vec<HbSlot>,
 }
 
 impl<P: HbProfile> HitboxInfo<P> {
@@ -464,6 +1462,15 @@ impl<P: HbProfile> HitboxInfo<P> {
             start_time,
             event_keys: TightSet::new(),
             overlaps: TightSet::new(),
+            ignored_overlaps: TightSet::new(),
+            proximities: TightSet::new(),
+            containers: TightSet::new(),
+            contents: TightSet::new(),
+            tile_contact: None,
+            out_of_bounds: false,
+            path: VecDeque::new(),
+            parent: None,
+            children: Vec::new(),
         }
     }
 
@@ -477,6 +1484,29 @@ impl<P: HbProfile> HitboxInfo<P> {
         result.to_dur_hitbox(time)
     }
 
+    // returns `hitbox` as it is filed in the grid, grown by the sensor margin
+    fn grid_hitbox<'a>(&self, hitbox: &'a DurHitbox) -> Cow<'a, DurHitbox> {
+        match self.profile.sensor_margin() {
+            Some(margin) => Cow::Owned(hitbox.padded(margin)),
+            None => Cow::Borrowed(hitbox),
+        }
+    }
+
+    // returns the velocity that takes the hitbox from `pos` at `time` to the
+    // next waypoint of its path, or keeps it still if there are none left
+    fn path_vel(&self, pos: Vec2, time: f64) -> HbVel {
+        let (value, end_time) = match self.path.front() {
+            Some(&(end_time, end_pos)) => ((end_pos - pos) * (1.0 / (end_time - time)), end_time),
+            None => (Vec2::zero(), f64::INFINITY),
+        };
+        HbVel {
+            value,
+            end_time,
+            orbit: None,
+            ..self.hitbox.vel.clone()
+        }
+    }
+
     fn pub_hitbox_at_time(&self, time: f64) -> Hitbox {
         assert!(
             time >= self.start_time && time <= self.pub_end_time,
@@ -489,6 +1519,64 @@ impl<P: HbProfile> HitboxInfo<P> {
     }
 }
 
+// batches smaller than this find their members by a linear scan rather than
+// through `Batch::indices`
+const MIN_INDEXED_BATCH: usize = 8;
+
+// hitboxes that were just added or updated, along with their previous and new
+// states, to be tracked together by `Collider::track_hitboxes`
+struct Batch {
+    updates: Vec<(HbSlot, Option<DurHitbox>, DurHitbox)>,
+    indices: FnvHashMap<HbSlot, usize>,
+}
+
+impl Batch {
+    fn new() -> Batch {
+        Batch {
+            updates: Vec::new(),
+            indices: FnvHashMap::default(),
+        }
+    }
+
+    fn index(&self, slot: HbSlot) -> Option<usize> {
+        if self.updates.len() < MIN_INDEXED_BATCH {
+            self.updates.iter().position(|update| update.0 == slot)
+        } else {
+            self.indices.get(&slot).cloned()
+        }
+    }
+
+    fn push(&mut self, slot: HbSlot, old_hitbox: Option<DurHitbox>, new_hitbox: DurHitbox) {
+        if let Some(index) = self.index(slot) {
+            // the grid still holds the hitbox at its first old state
+            self.updates[index].2 = new_hitbox;
+            return;
+        }
+        self.updates.push((slot, old_hitbox, new_hitbox));
+        if self.updates.len() == MIN_INDEXED_BATCH {
+            let indices = self.updates.iter().enumerate();
+            self.indices = indices.map(|(index, update)| (update.0, index)).collect();
+        } else if self.updates.len() > MIN_INDEXED_BATCH {
+            self.indices.insert(slot, self.updates.len() - 1);
+        }
+    }
+}
+
+// a batch index, the slot of another hitbox, and the kind of event to look
+// for between them
+type PairJob = (usize, HbSlot, PairKind);
+
+#[derive(Copy, Clone)]
+enum PairKind {
+    Separate { depth: f64, padding: f64 },
+    Collide { added: bool, depth: f64 },
+    Enter { margin: f64 },
+    Exit { margin: f64, padding: f64 },
+    // `outer` is true if the hitbox in the batch is the container
+    Contain { outer: bool },
+    Uncontain { outer: bool, padding: f64 },
+}
+
 /// A hitbox event type that may be returned from a `Collider` instance.
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
 pub enum HbEvent {
@@ -500,12 +1588,108 @@ pub enum HbEvent {
     /// A second `Collide` between two hitboxes may not occur before a
     /// `Separate`. A `Separate` event must come after a `Collide` event.
     Separate,
+
+    /// Occurs when a hitbox comes within the margin of a proximity sensor, as
+    /// described by `HbProfile::sensor_margin`.
+    Enter,
+
+    /// Occurs when a hitbox that was in proximity of a sensor moves out of its
+    /// margin.
+    ///
+    /// As with `Separate`, the hitboxes must be a small distance beyond the
+    /// margin before they are considered out of proximity.
+    Exit,
+
+    /// Occurs when a hitbox becomes entirely inside of a container, as
+    /// described by `HbProfile::is_container`.
+    Contain,
+
+    /// Occurs when a hitbox that was inside of a container extends outside of
+    /// it.
+    ///
+    /// As with `Separate`, the hitbox must extend a small distance outside of
+    /// the container before it is no longer considered inside.
+    Uncontain,
+
+    /// Occurs when a hitbox starts touching the solid tiles of the tile layer,
+    /// holding the tile that it touched first.
+    ///
+    /// A hitbox is considered to be touching the layer for as long as it
+    /// touches any of its solid tiles, so moving between adjacent tiles does
+    /// not generate events.
+    TileCollide(TileCoord),
+
+    /// Occurs when a hitbox is no longer touching any solid tile of the tile
+    /// layer, holding the tile that it touched last.
+    ///
+    /// As with `Separate`, the hitbox must be a small distance away from the
+    /// tiles before it is considered to have separated.
+    TileSeparate(TileCoord),
+}
+
+// returns true if a collision between the two hitboxes is reported, rather
+// than ignored due to their solid sides
+fn is_solid_collision<P: HbProfile>(
+    hb_1: &HitboxInfo<P>,
+    hitbox_1: &DurHitbox,
+    hb_2: &HitboxInfo<P>,
+    hitbox_2: &DurHitbox,
+    padding: f64,
+) -> bool {
+    let tolerance = padding + collide_depth(&hb_1.profile, &hb_2.profile);
+    is_solid_approach(&hb_1.profile, &hitbox_1.value, &hitbox_2.value, tolerance)
+        && is_solid_approach(&hb_2.profile, &hitbox_2.value, &hitbox_1.value, tolerance)
+}
+
+// returns true if `other` is touching `solid` through one of its solid sides,
+// to within `tolerance`
+fn is_solid_approach<P: HbProfile>(
+    profile: &P,
+    solid: &PlacedShape,
+    other: &PlacedShape,
+    tolerance: f64,
+) -> bool {
+    let mask = profile.solid_sides();
+    mask == CardMask::full() || other.masked_normal_from(solid, mask).len() <= tolerance
+}
+
+// returns the padding used to separate a pair of hitboxes, where `padding` is
+// the default
+fn pair_padding<P: HbProfile>(padding: f64, profile_1: &P, profile_2: &P) -> f64 {
+    let padding_1 = profile_1.padding().unwrap_or(padding);
+    let padding_2 = profile_2.padding().unwrap_or(padding);
+    padding_1.max(padding_2)
+}
+
+fn collide_depth<P: HbProfile>(profile_1: &P, profile_2: &P) -> f64 {
+    let depth = profile_1.collide_depth(profile_2);
+    assert!(depth >= 0.0, "collide depth must not be negative");
+    depth
+}
+
+fn separate_depth<P: HbProfile>(profile_1: &P, profile_2: &P) -> f64 {
+    let depth = profile_1.separate_depth(profile_2);
+    debug_assert!(
+        depth >= 0.0 && depth <= profile_1.collide_depth(profile_2),
+        "separate depth must be between 0.0 and the collide depth"
+    );
+    depth
+}
+
+// returns the margin used to track proximity between a pair of hitboxes, if
+// either is a sensor
+fn pair_margin<P: HbProfile>(profile_1: &P, profile_2: &P) -> Option<f64> {
+    match (profile_1.sensor_margin(), profile_2.sensor_margin()) {
+        (Some(margin_1), Some(margin_2)) => Some(margin_1.max(margin_2)),
+        (margin_1, margin_2) => margin_1.or(margin_2),
+    }
 }
 
-fn new_event(event: HbEvent, mut id_1: HbId, mut id_2: HbId) -> (HbEvent, HbId, HbId) {
+fn new_event<P: HbProfile>(event: HbEvent, mut profile_1: P, mut profile_2: P) -> (HbEvent, P, P) {
+    let (id_1, id_2) = (profile_1.id(), profile_2.id());
     assert!(id_1 != id_2, "ids must be different: {} {}", id_1, id_2);
     if id_1 > id_2 {
-        mem::swap(&mut id_1, &mut id_2);
+        mem::swap(&mut profile_1, &mut profile_2);
     }
-    (event, id_1, id_2)
+    (event, profile_1, profile_2)
 }
@@ -14,7 +14,7 @@
 
 mod solvers;
 
-use crate::core;
+use crate::core::{self, Orbit};
 use crate::geom::shape::PlacedBounds;
 use crate::geom::*;
 use std::f64;
@@ -28,6 +28,8 @@ use std::f64;
 pub struct DurHbVel {
     pub value: Vec2,
     pub resize: Vec2,
+    pub angular: f64,
+    pub orbit: Option<Orbit>,
     pub duration: f64,
 }
 
@@ -36,18 +38,28 @@ impl DurHbVel {
         DurHbVel {
             value: Vec2::zero(),
             resize: Vec2::zero(),
+            angular: 0.0,
+            orbit: None,
             duration: f64::INFINITY,
         }
     }
 
     fn is_still(&self) -> bool {
-        self.value == Vec2::zero() && self.resize == Vec2::zero()
+        self.value == Vec2::zero()
+            && self.resize == Vec2::zero()
+            && self.angular == 0.0
+            && self.orbit.is_none()
     }
 
     fn negate(&self) -> DurHbVel {
         DurHbVel {
             value: -self.value,
             resize: -self.resize,
+            angular: -self.angular,
+            orbit: self.orbit.map(|orbit| Orbit {
+                angular: -orbit.angular,
+                ..orbit
+            }),
             duration: self.duration,
         }
     }
@@ -57,8 +69,8 @@ impl PlacedBounds for DurHbVel {
     fn bounds_center(&self) -> &Vec2 {
         &self.value
     }
-    fn bounds_dims(&self) -> &Vec2 {
-        &self.resize
+    fn bounds_dims(&self) -> Vec2 {
+        self.resize
     }
 }
 
@@ -82,7 +94,33 @@ impl DurHitbox {
             "requires time < {}",
             core::HIGH_TIME
         );
-        self.value.advance(self.vel.value, self.vel.resize, time)
+        let mut result = self
+            .value
+            .advance(self.vel.value, self.vel.resize, time)
+            .rotated(self.vel.angular * time);
+        if let Some(orbit) = self.vel.orbit {
+            result.pos = orbit.advance(self.value.pos, time);
+        }
+        result
+    }
+
+    pub fn advanced(&self, time: f64) -> DurHitbox {
+        DurHitbox {
+            value: self.advanced_shape(time),
+            vel: DurHbVel {
+                duration: self.vel.duration - time,
+                ..self.vel.clone()
+            },
+        }
+    }
+
+    pub fn padded(&self, padding: f64) -> DurHitbox {
+        let mut result = self.clone();
+        result.value.shape = self
+            .value
+            .shape
+            .resized(self.value.dims() + v2(padding, padding) * 2.0);
+        result
     }
 
     pub fn bounding_box(&self) -> PlacedShape {
@@ -92,12 +130,72 @@ impl DurHitbox {
     pub fn bounding_box_for(&self, duration: f64) -> PlacedShape {
         if self.vel.is_still() {
             self.value.as_rect()
+        } else if let Some(orbit) = self.vel.orbit {
+            // the hitbox stays within reach of the circle that it moves along
+            let end_value = self.advanced_shape(duration);
+            let reach = self
+                .value
+                .shape
+                .circumradius()
+                .max(end_value.shape.circumradius());
+            let radius = self.value.pos.dist(&orbit.center);
+            Shape::square((radius + reach) * 2.0).place(orbit.center)
+        } else if self.is_turning() {
+            // the corners of a turning rect stay within its circumcircle
+            let end_value = self.advanced_shape(duration);
+            circumsquare(&self.value).bounding_box(&circumsquare(&end_value))
         } else {
             let end_value = self.advanced_shape(duration);
             self.value.bounding_box(&end_value)
         }
     }
 
+    // returns true if the bounding box of the hitbox does not resize at the
+    // rate of `vel.resize`, since its shape is rotated
+    fn is_turning(&self) -> bool {
+        self.value.kind() == ShapeKind::OrientedRect
+            && (self.vel.angular != 0.0 || self.vel.resize != Vec2::zero())
+    }
+
+    // returns an upper bound on how fast any point of the hitbox moves over
+    // the next `duration`, ignoring its linear velocity
+    pub fn turn_speed(&self, duration: f64) -> f64 {
+        let mut radius = self.value.shape.circumradius();
+        if self.vel.angular != 0.0 && duration > 0.0 {
+            // the circumradius is convex in time, so it peaks at an endpoint
+            radius = radius.max(self.advanced_shape(duration).shape.circumradius());
+        }
+        let orbit_speed = self
+            .vel
+            .orbit
+            .map_or(0.0, |orbit| orbit.speed(self.value.pos));
+        self.vel.angular.abs() * radius + self.vel.resize.len() * 0.5 + orbit_speed
+    }
+
+    // returns the time until the hitbox starts to extend outside of `bounds`,
+    // or infinity if that does not happen within its duration
+    pub fn exit_time(&self, bounds: &PlacedShape) -> f64 {
+        if self.is_turning() || self.vel.orbit.is_some() {
+            return solvers::exit_time(self, bounds);
+        }
+        let mut result = f64::INFINITY;
+        for &card in &Card::values() {
+            let gap = bounds.edge(card) - self.value.edge(card);
+            if gap < 0.0 {
+                return 0.0;
+            }
+            let rate = self.vel.edge(card);
+            if rate > 0.0 {
+                result = result.min(gap / rate);
+            }
+        }
+        if result > self.vel.duration {
+            f64::INFINITY
+        } else {
+            result
+        }
+    }
+
     pub fn collide_time(&self, other: &DurHitbox) -> f64 {
         solvers::collide_time(self, other)
     }
@@ -105,11 +203,78 @@ impl DurHitbox {
     pub fn separate_time(&self, other: &DurHitbox, padding: f64) -> f64 {
         solvers::separate_time(self, other, padding)
     }
+
+    // returns the time until the hitbox overlaps `other` by more than `depth`,
+    // measured as the length of `PlacedShape::normal_from`
+    pub fn deep_collide_time(&self, other: &DurHitbox, depth: f64) -> f64 {
+        if depth == 0.0 {
+            self.collide_time(other)
+        } else {
+            let (a, b) = self.shrunk_for_depth(other, depth);
+            a.collide_time(&b)
+        }
+    }
+
+    // returns the time until the hitbox overlaps `other` by less than `depth`,
+    // with the same `padding` as `separate_time`
+    pub fn deep_separate_time(&self, other: &DurHitbox, depth: f64, padding: f64) -> f64 {
+        if depth == 0.0 {
+            self.separate_time(other, padding)
+        } else {
+            let (a, b) = self.shrunk_for_depth(other, depth);
+            a.separate_time(&b, padding)
+        }
+    }
+
+    // Shrinks the hitboxes so that they touch exactly when the originals
+    // overlap by `depth`. Hitboxes of the same kind are shrunk evenly, but
+    // only the circle is shrunk in a rect-circle pair, since shrinking the
+    // rect would move its corners diagonally. A half-plane is always the one
+    // that is shrunk, since that just moves its edge. A hitbox that is too
+    // small for its share of the depth is only shrunk down to a point or a
+    // line.
+    fn shrunk_for_depth(&self, other: &DurHitbox, depth: f64) -> (DurHitbox, DurHitbox) {
+        let (shrink_1, shrink_2) = match (self.value.kind(), other.value.kind()) {
+            (ShapeKind::HalfPlane, _) => (depth, 0.0),
+            (_, ShapeKind::HalfPlane) => (0.0, depth),
+            (ShapeKind::Circle, ShapeKind::Circle) => (depth * 0.5, depth * 0.5),
+            (_, ShapeKind::Circle) => (0.0, depth),
+            (ShapeKind::Circle, _) => (depth, 0.0),
+            _ => (depth * 0.5, depth * 0.5),
+        };
+        (self.shrunk(shrink_1), other.shrunk(shrink_2))
+    }
+
+    fn shrunk(&self, shrink: f64) -> DurHitbox {
+        if self.value.kind() == ShapeKind::HalfPlane {
+            self.padded(-shrink)
+        } else {
+            let dims = self.value.dims();
+            self.padded(-shrink.min(dims.x.min(dims.y) * 0.5))
+        }
+    }
+
+    // returns the time until the hitbox is entirely inside of `outer`
+    pub fn contain_time(&self, outer: &DurHitbox) -> f64 {
+        solvers::contain_time(self, outer)
+    }
+
+    // returns the time until the hitbox, which is inside of `outer`, extends
+    // more than `padding` outside of it
+    pub fn uncontain_time(&self, outer: &DurHitbox, padding: f64) -> f64 {
+        solvers::uncontain_time(self, outer, padding)
+    }
+}
+
+// returns the square around the circumcircle of `shape`
+fn circumsquare(shape: &PlacedShape) -> PlacedShape {
+    Shape::square(shape.shape.circumradius() * 2.0).place(shape.pos)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::core::dur_hitbox::DurHitbox;
+    use crate::core::Orbit;
     use crate::geom::*;
     use std::f64;
 
@@ -264,6 +429,329 @@ mod tests {
         assert_eq!(a.collide_time(&b), 0.0);
     }
 
+    #[test]
+    fn test_deep_collision() {
+        let mut a = DurHitbox::new(PlacedShape::new(v2(-10.0, 0.0), Shape::square(2.0)));
+        a.vel.value = v2(1.0, 0.0);
+        a.vel.duration = 100.0;
+        let b = DurHitbox::new(PlacedShape::new(v2(0.0, 0.5), Shape::square(2.0)));
+        assert_eq!(a.deep_collide_time(&b, 0.0), 8.0);
+        assert_eq!(a.deep_collide_time(&b, 1.0), 9.0);
+        // the overlap along y limits the depth to 1.5
+        assert_eq!(a.deep_collide_time(&b, 1.6), f64::INFINITY);
+        // both squares are shrunk to points, which pass by each other
+        assert_eq!(a.deep_collide_time(&b, 3.0), f64::INFINITY);
+        let a = a.advanced(9.5);
+        assert!((a.deep_separate_time(&b, 1.0, 0.1) - 1.6).abs() < 1e-9);
+        assert!((a.deep_separate_time(&b, 0.5, 0.1) - 2.1).abs() < 1e-9);
+
+        let mut c = DurHitbox::new(PlacedShape::new(v2(-10.0, 0.0), Shape::circle(2.0)));
+        c.vel.value = v2(1.0, 0.0);
+        c.vel.duration = 100.0;
+        let d = DurHitbox::new(PlacedShape::new(v2(0.0, 1.25), Shape::square(2.0)));
+        let expected_time = 9.0 - 0.1875f64.sqrt();
+        assert!((c.deep_collide_time(&d, 0.5) - expected_time).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rect_rect_containment() {
+        let mut a = DurHitbox::new(PlacedShape::new(v2(-10.0, 0.0), Shape::square(2.0)));
+        a.vel.value = v2(1.0, 0.0);
+        a.vel.duration = 100.0;
+        let mut b = DurHitbox::new(PlacedShape::new(v2(0.0, 0.0), Shape::rect(v2(6.0, 4.0))));
+        b.vel.duration = 100.0;
+        assert_eq!(a.contain_time(&b), 8.0);
+        assert_eq!(b.contain_time(&a), f64::INFINITY);
+        let a = a.advanced(8.0);
+        assert!((a.uncontain_time(&b, 0.1) - 4.1).abs() < 1e-9);
+        assert_eq!(a.contain_time(&b), 0.0);
+    }
+
+    #[test]
+    fn test_circle_circle_containment() {
+        let mut a = DurHitbox::new(PlacedShape::new(v2(0.0, 0.0), Shape::circle(2.0)));
+        a.vel.duration = 100.0;
+        let mut b = DurHitbox::new(PlacedShape::new(v2(-10.0, 0.0), Shape::circle(6.0)));
+        b.vel.value = v2(1.0, 0.0);
+        b.vel.duration = 100.0;
+        assert_eq!(a.contain_time(&b), 8.0);
+        let b = b.advanced(8.0);
+        assert!((a.uncontain_time(&b, 0.5) - 4.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rect_circle_containment() {
+        let mut a = DurHitbox::new(PlacedShape::new(v2(0.0, 0.0), Shape::square(2.0)));
+        a.vel.duration = 100.0;
+        let mut b = DurHitbox::new(PlacedShape::new(v2(-10.0, 0.0), Shape::circle(4.0)));
+        b.vel.value = v2(1.0, 0.0);
+        b.vel.duration = 100.0;
+        let expected_time = 11.0 - 3f64.sqrt();
+        assert!((a.contain_time(&b) - expected_time).abs() < 1e-9);
+
+        // a circle is contained in a rect when its bounding box is
+        let mut c = DurHitbox::new(PlacedShape::new(v2(-10.0, 0.0), Shape::circle(2.0)));
+        c.vel.value = v2(1.0, 0.0);
+        c.vel.duration = 100.0;
+        let d = DurHitbox::new(PlacedShape::new(v2(0.0, 0.0), Shape::rect(v2(6.0, 4.0))));
+        assert_eq!(c.contain_time(&d), 8.0);
+    }
+
+    #[test]
+    fn test_no_containment() {
+        let mut a = DurHitbox::new(PlacedShape::new(v2(-10.0, 0.0), Shape::square(4.0)));
+        a.vel.value = v2(1.0, 0.0);
+        a.vel.duration = 100.0;
+        let b = DurHitbox::new(PlacedShape::new(v2(0.0, 0.0), Shape::circle(4.0)));
+        assert_eq!(a.contain_time(&b), f64::INFINITY);
+        a.value.shape = Shape::square(2.0);
+        a.vel.duration = 5.0;
+        assert_eq!(a.contain_time(&b), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_spinning_collision() {
+        let mut a = DurHitbox::new(PlacedShape::new(
+            v2(0.0, 0.0),
+            Shape::oriented_rect(v2(6.0, 0.5), 0.0),
+        ));
+        a.vel.angular = 1.0;
+        a.vel.duration = 100.0;
+        let mut b = DurHitbox::new(PlacedShape::new(v2(0.0, 3.0), Shape::rect(v2(10.0, 1.0))));
+        b.vel.duration = 100.0;
+        let radius = v2(3.0, 0.25).len();
+        let tilt = (0.25f64 / 3.0).atan();
+        let expected_time = (2.5 / radius).asin() - tilt;
+        assert!((a.collide_time(&b) - expected_time).abs() < 1e-6);
+        assert!((b.collide_time(&a) - expected_time).abs() < 1e-6);
+        assert_eq!(a.separate_time(&b, 0.1), 0.0);
+
+        // the padding is applied to the axis-aligned rect
+        let a = a.advanced(expected_time);
+        let expected_time = f64::consts::PI - (2.4 / radius).asin() + tilt - expected_time;
+        assert!((a.separate_time(&b, 0.1) - expected_time).abs() < 1e-6);
+        assert!((b.separate_time(&a, 0.1) - expected_time).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_spinning_containment() {
+        let mut a = DurHitbox::new(PlacedShape::new(
+            v2(0.0, 0.0),
+            Shape::oriented_rect(v2(2.0, 2.0), 0.0),
+        ));
+        a.vel.angular = 1.0;
+        a.vel.duration = 100.0;
+        let b = DurHitbox::new(PlacedShape::new(v2(0.0, 0.0), Shape::square(2.5)));
+        let expected_time = (1.25 / 2f64.sqrt()).asin() - f64::consts::FRAC_PI_4;
+        assert_eq!(a.contain_time(&b), 0.0);
+        assert!((a.uncontain_time(&b, 0.0) - expected_time).abs() < 1e-6);
+        let bounds = b.value;
+        assert!((a.exit_time(&bounds) - expected_time).abs() < 1e-6);
+
+        // the corners never leave the circumcircle
+        let c = DurHitbox::new(PlacedShape::new(v2(0.0, 0.0), Shape::circle(3.0)));
+        assert_eq!(a.contain_time(&c), 0.0);
+        assert_eq!(a.uncontain_time(&c, 0.0), f64::INFINITY);
+
+        // the corners stay just inside, which must not be mistaken for a root
+        let d = DurHitbox::new(PlacedShape::new(
+            v2(0.0, 0.0),
+            Shape::circle(2.0 * 2f64.sqrt() + 2e-4),
+        ));
+        assert_eq!(a.contain_time(&d), 0.0);
+        assert_eq!(a.uncontain_time(&d, 0.0), f64::INFINITY);
+    }
+
+    static TRIANGLE: [Vec2; 3] = [
+        Vec2 { x: -1.0, y: -1.0 },
+        Vec2 { x: 1.0, y: -1.0 },
+        Vec2 { x: -1.0, y: 1.0 },
+    ];
+
+    #[test]
+    fn test_polygon_rect_collision() {
+        let mut a = DurHitbox::new(PlacedShape::new(v2(-5.0, 0.0), Shape::polygon(&TRIANGLE)));
+        a.vel.value = v2(1.0, 0.0);
+        a.vel.duration = 100.0;
+        let mut b = DurHitbox::new(PlacedShape::new(v2(3.0, 0.0), Shape::square(2.0)));
+        b.vel.duration = 100.0;
+        assert_eq!(a.collide_time(&b), 6.0);
+        assert_eq!(b.collide_time(&a), 6.0);
+        assert_eq!(a.separate_time(&b, 0.0), 0.0);
+
+        let a = a.advanced(6.0);
+        assert_eq!(a.collide_time(&b), 0.0);
+        assert_eq!(a.separate_time(&b, 0.0), 4.0);
+        assert_eq!(b.separate_time(&a, 0.0), 4.0);
+    }
+
+    #[test]
+    fn test_polygon_circle_collision() {
+        let mut a = DurHitbox::new(PlacedShape::new(v2(0.0, 0.0), Shape::polygon(&TRIANGLE)));
+        a.vel.value = v2(1.0, 0.0);
+        a.vel.duration = 100.0;
+        let mut b = DurHitbox::new(PlacedShape::new(v2(4.0, -1.6), Shape::circle(2.0)));
+        b.vel.duration = 100.0;
+
+        // the circle reaches the corner after the edges pushed out by its radius
+        assert!((a.collide_time(&b) - 2.2).abs() < 1e-9);
+        assert!((b.collide_time(&a) - 2.2).abs() < 1e-9);
+
+        let a = a.advanced(2.2);
+        assert!((a.separate_time(&b, 0.0) - 3.6).abs() < 1e-9);
+        assert!((b.separate_time(&a, 0.0) - 3.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ellipse_rect_collision() {
+        let mut a = DurHitbox::new(PlacedShape::new(
+            v2(-10.0, 0.0),
+            Shape::ellipse(v2(4.0, 2.0)),
+        ));
+        a.vel.value = v2(1.0, 0.0);
+        a.vel.duration = 100.0;
+        let mut b = DurHitbox::new(PlacedShape::new(v2(3.0, 0.0), Shape::square(2.0)));
+        b.vel.duration = 100.0;
+        assert!((a.collide_time(&b) - 10.0).abs() < 1e-6);
+        assert!((b.collide_time(&a) - 10.0).abs() < 1e-6);
+
+        let a = a.advanced(10.0);
+        assert!((a.separate_time(&b, 0.1) - 6.1).abs() < 1e-6);
+        assert!((b.separate_time(&a, 0.1) - 6.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_deep_ellipse_collision() {
+        // the ellipse is too thin for the depth, so it is only shrunk to a line
+        let mut a = DurHitbox::new(PlacedShape::new(
+            v2(-10.0, 0.0),
+            Shape::ellipse(v2(4.0, 1.0)),
+        ));
+        a.vel.value = v2(1.0, 0.0);
+        a.vel.duration = 100.0;
+        let mut b = DurHitbox::new(PlacedShape::new(v2(3.0, 0.0), Shape::square(2.0)));
+        b.vel.duration = 100.0;
+        assert!((a.deep_collide_time(&b, 1.0) - 10.5).abs() < 1e-6);
+        assert!((b.deep_collide_time(&a, 1.0) - 10.5).abs() < 1e-6);
+
+        let a = a.advanced(11.0);
+        assert!((a.deep_separate_time(&b, 1.0, 0.1) - 4.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ellipse_containment() {
+        let mut a = DurHitbox::new(PlacedShape::new(
+            v2(-10.0, 0.0),
+            Shape::ellipse(v2(4.0, 2.0)),
+        ));
+        a.vel.value = v2(1.0, 0.0);
+        a.vel.duration = 100.0;
+        let b = DurHitbox::new(PlacedShape::new(v2(0.0, 0.0), Shape::circle(6.0)));
+        assert!((a.contain_time(&b) - 9.0).abs() < 1e-6);
+
+        // a circle stretched into an ellipse
+        let mut c = DurHitbox::new(PlacedShape::new(v2(0.0, 0.0), Shape::circle(2.0)));
+        c.vel.resize = v2(2.0, 0.0);
+        c.vel.duration = 100.0;
+        let d = DurHitbox::new(PlacedShape::new(v2(0.0, 0.0), Shape::square(4.0)));
+        assert_eq!(c.contain_time(&d), 0.0);
+        assert!((c.uncontain_time(&d, 0.0) - 1.0).abs() < 1e-6);
+        assert_eq!(c.exit_time(&d.value), 1.0);
+    }
+
+    #[test]
+    fn test_half_plane_collision() {
+        let floor = DurHitbox::new(Shape::half_plane(Card::PlusY.into()).place(v2(0.0, 0.0)));
+        let mut a = DurHitbox::new(PlacedShape::new(v2(100.0, 10.0), Shape::square(2.0)));
+        a.vel.value = v2(3.0, -1.0);
+        a.vel.duration = 100.0;
+        assert_eq!(a.collide_time(&floor), 9.0);
+        assert_eq!(floor.collide_time(&a), 9.0);
+
+        let mut a = a.advanced(9.0);
+        a.vel.value = v2(3.0, 1.0);
+        assert_eq!(a.separate_time(&floor, 0.5), 0.5);
+        assert_eq!(floor.separate_time(&a, 0.5), 0.5);
+
+        // a tilted half-plane and a spinning rect
+        let wall = DurHitbox::new(Shape::half_plane(v2(1.0, 1.0)).place(v2(0.0, 0.0)));
+        let mut b = DurHitbox::new(PlacedShape::new(
+            v2(5.0, 5.0),
+            Shape::oriented_rect(v2(2.0, 2.0), 0.0),
+        ));
+        b.vel.value = v2(-1.0, -1.0);
+        b.vel.angular = 1.0;
+        b.vel.duration = 100.0;
+        let time = b.collide_time(&wall);
+        assert!(time > 4.0 && time < 4.5);
+        let b = b.advanced(time);
+        assert!(b.value.normal_from(&wall.value).len().abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_half_plane_containment() {
+        let floor = DurHitbox::new(Shape::half_plane(Card::PlusY.into()).place(v2(0.0, 0.0)));
+        let mut a = DurHitbox::new(PlacedShape::new(v2(0.0, 10.0), Shape::circle(2.0)));
+        a.vel.value = v2(0.0, -1.0);
+        a.vel.duration = 100.0;
+        assert!((a.contain_time(&floor) - 11.0).abs() < 1e-6);
+        assert_eq!(floor.contain_time(&a), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_orbit_circle_collision() {
+        let mut a = DurHitbox::new(PlacedShape::new(v2(5.0, 0.0), Shape::circle(2.0)));
+        a.vel.orbit = Some(Orbit {
+            center: v2(0.0, 0.0),
+            angular: 1.0,
+        });
+        a.vel.duration = 100.0;
+        let b = DurHitbox::new(PlacedShape::new(v2(0.0, 5.0), Shape::circle(2.0)));
+        let expected = f64::consts::FRAC_PI_2 - 2.0 * (0.2f64).asin();
+        assert!((a.collide_time(&b) - expected).abs() < 1e-6);
+        assert!((b.collide_time(&a) - expected).abs() < 1e-6);
+
+        let a = a.advanced(f64::consts::FRAC_PI_2);
+        let expected = 2.0 * (0.21f64).asin();
+        assert!((a.separate_time(&b, 0.1) - expected).abs() < 1e-6);
+        assert!((b.separate_time(&a, 0.1) - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_orbit_near_miss() {
+        // the top of the orbit passes 1e-4 below `b` without ever touching it
+        let mut a = DurHitbox::new(PlacedShape::new(v2(0.0, 5.0), Shape::square(2.0)));
+        a.vel.orbit = Some(Orbit {
+            center: v2(0.0, 0.0),
+            angular: 1.0,
+        });
+        a.vel.duration = 100.0;
+        let b = DurHitbox::new(PlacedShape::new(
+            v2(0.0, 6.5001),
+            Shape::rect(v2(20.0, 1.0)),
+        ));
+        assert_eq!(a.collide_time(&b), f64::INFINITY);
+        assert_eq!(b.collide_time(&a), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_orbit_rect_collision() {
+        let mut a = DurHitbox::new(PlacedShape::new(v2(0.0, -5.0), Shape::square(2.0)));
+        a.vel.orbit = Some(Orbit {
+            center: v2(0.0, 0.0),
+            angular: 1.0,
+        });
+        a.vel.duration = 100.0;
+        let b = DurHitbox::new(PlacedShape::new(v2(-5.0, 5.0), Shape::square(2.0)));
+
+        // the orbit reaches `b` from its right side
+        let time = a.collide_time(&b);
+        assert!(time > f64::consts::PI && time < 1.5 * f64::consts::PI);
+        let a = a.advanced(time);
+        assert!(a.value.normal_from(&b.value).len().abs() < 1e-6);
+        assert!((a.value.pos.x + 3.0).abs() < 1e-6);
+    }
+
     #[test]
     fn test_low_duration() {
         let sqrt2 = (2.0f64).sqrt();
@@ -14,13 +14,20 @@
 
 use crate::core;
 use crate::core::dur_hitbox::DurHitbox;
-use crate::geom::shape::PlacedBounds;
+use crate::float::n64;
+use crate::geom::shape::{min_over_dirs, PlacedBounds, Sector};
 use crate::geom::*;
 use crate::util;
+use std::cmp::Ordering;
 use std::f64;
+use std::iter;
 
 // This module contains methods to solve for the collision/separation time
-// of two hitboxes.
+// of two hitboxes, and for the time that one hitbox becomes or stops being
+// contained in another. Hitboxes that rotate, orbit or involve an ellipse
+// have no closed form solution, and are solved by conservative advancement
+// instead.
+// Half-planes never move, so they are solved along their normal.
 
 pub fn collide_time(a: &DurHitbox, b: &DurHitbox) -> f64 {
     let duration = a.vel.duration.min(b.vel.duration);
@@ -35,20 +42,30 @@ pub fn collide_time(a: &DurHitbox, b: &DurHitbox) -> f64 {
 
 pub fn separate_time(a: &DurHitbox, b: &DurHitbox, padding: f64) -> f64 {
     let (a, b) = match (a.value.kind(), b.value.kind()) {
-        (ShapeKind::Rect, ShapeKind::Circle) => (b, a),
+        (ShapeKind::Rect, ShapeKind::Circle)
+        | (ShapeKind::OrientedRect, ShapeKind::Circle)
+        | (ShapeKind::OrientedRect, ShapeKind::Rect)
+        | (ShapeKind::Polygon, ShapeKind::Circle)
+        | (ShapeKind::Polygon, ShapeKind::Rect)
+        | (_, ShapeKind::HalfPlane) => (b, a),
         _ => (a, b),
     };
-    let mut a = a.clone();
-    a.value.shape = Shape::new(a.value.kind(), a.value.dims() + v2(padding, padding) * 2.0);
+    let a = a.padded(padding);
     time_unpadded(&a, b, false, a.vel.duration.min(b.vel.duration))
 }
 
 fn time_unpadded(a: &DurHitbox, b: &DurHitbox, for_collide: bool, duration: f64) -> f64 {
     let result = match (a.value.kind(), b.value.kind()) {
+        (ShapeKind::HalfPlane, _) => half_plane_time(a, b, for_collide, duration),
+        (_, ShapeKind::HalfPlane) => half_plane_time(b, a, for_collide, duration),
+        _ if needs_stepping(a) || needs_stepping(b) => stepped_time(a, b, for_collide, duration),
         (ShapeKind::Rect, ShapeKind::Rect) => rect_rect_time(a, b, for_collide),
         (ShapeKind::Circle, ShapeKind::Circle) => circle_circle_time(a, b, for_collide),
         (ShapeKind::Rect, ShapeKind::Circle) => rect_circle_time(a, b, for_collide, duration),
         (ShapeKind::Circle, ShapeKind::Rect) => rect_circle_time(b, a, for_collide, duration),
+        (_, ShapeKind::Circle) => polygon_circle_time(a, b, for_collide, duration),
+        (ShapeKind::Circle, _) => polygon_circle_time(b, a, for_collide, duration),
+        _ => convex_time(a, b, for_collide),
     };
     if result >= duration {
         f64::INFINITY
@@ -57,12 +74,83 @@ fn time_unpadded(a: &DurHitbox, b: &DurHitbox, for_collide: bool, duration: f64)
     }
 }
 
+// returns true if pairs involving `hitbox` must be solved by `stepped_time`
+fn needs_stepping(hitbox: &DurHitbox) -> bool {
+    hitbox.vel.angular != 0.0 || hitbox.vel.orbit.is_some() || is_elliptical(hitbox)
+}
+
+// returns true if the hitbox is an ellipse, or is a circle becoming one
+fn is_elliptical(hitbox: &DurHitbox) -> bool {
+    hitbox.value.shape.is_ellipse()
+        || (hitbox.value.kind() == ShapeKind::Circle && hitbox.vel.resize.x != hitbox.vel.resize.y)
+}
+
+// handles pairs of hitboxes that rotate, orbit or involve an ellipse, which
+// are not solved exactly
+fn stepped_time(a: &DurHitbox, b: &DurHitbox, for_collide: bool, duration: f64) -> f64 {
+    let sign = if for_collide { -1.0 } else { 1.0 };
+    advancement_time(a, b, duration, |a, b| sign * a.normal_from(b).len())
+}
+
 fn rect_rect_time(a: &DurHitbox, b: &DurHitbox, for_collide: bool) -> f64 {
+    let cards = Card::values();
+    let overlaps = cards.iter().map(|&card| {
+        (
+            a.value.card_overlap(&b.value, card),
+            a.vel.card_overlap(&b.vel, card),
+        )
+    });
+    overlap_time(overlaps, for_collide)
+}
+
+// the overlap with a half-plane only changes along its normal, where it
+// changes linearly unless the other hitbox turns or is elliptical
+fn half_plane_time(
+    half_plane: &DurHitbox,
+    other: &DurHitbox,
+    for_collide: bool,
+    duration: f64,
+) -> f64 {
+    assert!(
+        other.value.kind() != ShapeKind::HalfPlane,
+        "half-planes do not interact with each other"
+    );
+    if needs_stepping(other) {
+        return stepped_time(half_plane, other, for_collide, duration);
+    }
+    let normal = half_plane.value.shape.axis();
+    let overlap = half_plane.value.normal_from(&other.value).len();
+    let overlap_vel = support_vel(half_plane, normal) + support_vel(other, -normal);
+    overlap_time(iter::once((overlap, overlap_vel)), for_collide)
+}
+
+// generalizes `rect_rect_time` to the edge normals of rects and polygons
+fn convex_time(a: &DurHitbox, b: &DurHitbox, for_collide: bool) -> f64 {
+    let overlaps = a
+        .value
+        .edge_normals()
+        .into_iter()
+        .chain(b.value.edge_normals())
+        .flat_map(|axis| vec![axis, -axis])
+        .map(|axis| {
+            (
+                b.value.support(axis) + a.value.support(-axis),
+                support_vel(b, axis) + support_vel(a, -axis),
+            )
+        });
+    overlap_time(overlaps, for_collide)
+}
+
+// Solves for the time when the shapes start (or stop) overlapping along every
+// one of a set of separating axes, given the overlap along each axis and its
+// rate of change.
+fn overlap_time<I>(overlaps: I, for_collide: bool) -> f64
+where
+    I: Iterator<Item = (f64, f64)>,
+{
     let mut overlap_start = 0.0f64;
     let mut overlap_end = f64::INFINITY;
-    for &card in &Card::values() {
-        let overlap = a.value.card_overlap(&b.value, card);
-        let overlap_vel = a.vel.card_overlap(&b.vel, card);
+    for (overlap, overlap_vel) in overlaps {
         if overlap < 0.0 {
             if !for_collide {
                 return 0.0;
@@ -85,6 +173,20 @@ fn rect_rect_time(a: &DurHitbox, b: &DurHitbox, for_collide: bool) -> f64 {
     }
 }
 
+// returns the rate at which the support of a non-rotating hitbox along the
+// unit vector `dir` increases
+fn support_vel(hitbox: &DurHitbox, dir: Vec2) -> f64 {
+    let resize = hitbox.vel.resize;
+    let extent_vel = match hitbox.value.kind() {
+        ShapeKind::Circle | ShapeKind::Polygon | ShapeKind::HalfPlane => resize.x * 0.5,
+        ShapeKind::Rect | ShapeKind::OrientedRect => {
+            let local = hitbox.value.shape.local_vec(dir);
+            (resize.x * local.x.abs() + resize.y * local.y.abs()) * 0.5
+        }
+    };
+    hitbox.vel.value * dir + extent_vel
+}
+
 fn circle_circle_time(a: &DurHitbox, b: &DurHitbox, for_collide: bool) -> f64 {
     let sign = if for_collide { 1.0 } else { -1.0 };
 
@@ -163,3 +265,357 @@ fn rebased_rect_circle_collide_time(rect: &DurHitbox, circle: &DurHitbox) -> f64
         0.0
     }
 }
+
+fn polygon_circle_time(
+    poly: &DurHitbox,
+    circle: &DurHitbox,
+    for_collide: bool,
+    duration: f64,
+) -> f64 {
+    if poly.vel.resize != Vec2::zero() {
+        // the corners of a resizing rect do not move at a single velocity
+        stepped_time(poly, circle, for_collide, duration)
+    } else if for_collide {
+        polygon_circle_collide_time(poly, circle, duration)
+    } else {
+        polygon_circle_separate_time(poly, circle)
+    }
+}
+
+fn polygon_circle_collide_time(poly: &DurHitbox, circle: &DurHitbox, duration: f64) -> f64 {
+    let base_time = convex_time(poly, circle, true);
+    if base_time >= duration {
+        f64::INFINITY
+    } else {
+        let mut poly = poly.clone();
+        poly.value = poly.advanced_shape(base_time);
+        let mut circle = circle.clone();
+        circle.value = circle.advanced_shape(base_time);
+
+        base_time + rebased_polygon_circle_collide_time(&poly, &circle)
+    }
+}
+
+fn polygon_circle_separate_time(poly: &DurHitbox, circle: &DurHitbox) -> f64 {
+    let base_time = convex_time(poly, circle, false);
+    if base_time == 0.0 {
+        return 0.0;
+    }
+    if base_time >= core::HIGH_TIME {
+        return f64::INFINITY;
+    }
+
+    let mut poly = poly.clone();
+    poly.value = poly.advanced_shape(base_time);
+    poly.vel = poly.vel.negate();
+
+    let mut circle = circle.clone();
+    circle.value = circle.advanced_shape(base_time);
+    circle.vel = circle.vel.negate();
+
+    (base_time - rebased_polygon_circle_collide_time(&poly, &circle)).max(0.0)
+}
+
+// The circle is touching the polygon with its edges pushed out by the circle's
+// radius. It is touching the polygon itself unless its center lies beyond a
+// corner, in which case it still has to reach the rounded corner.
+fn rebased_polygon_circle_collide_time(poly: &DurHitbox, circle: &DurHitbox) -> f64 {
+    let center = circle.value.pos;
+    let corners = poly.value.corners();
+    let nearest_corner = (0..corners.len())
+        .map(|i| (corners[i], corners[(i + 1) % corners.len()]))
+        .map(|(start, end)| {
+            let edge = end - start;
+            let progress = (center - start) * edge;
+            if progress <= 0.0 {
+                (start.dist_sq(&center), Some(start))
+            } else if progress >= edge.len_sq() {
+                (end.dist_sq(&center), Some(end))
+            } else {
+                let offset = (center - start) * v2(edge.y, -edge.x);
+                (offset * offset / edge.len_sq(), None)
+            }
+        })
+        .min_by_key(|&(dist_sq, _)| n64(dist_sq))
+        .unwrap()
+        .1;
+    if let Some(corner) = nearest_corner {
+        let mut corner = DurHitbox::new(PlacedShape::new(corner, Shape::circle(0.0)));
+        corner.vel.value = poly.vel.value;
+        let padding = poly.value.shape.polygon_padding();
+        let circle = if padding == 0.0 {
+            circle.clone()
+        } else {
+            circle.padded(padding)
+        };
+        circle_circle_time(&corner, &circle, true)
+    } else {
+        0.0
+    }
+}
+
+pub fn contain_time(inner: &DurHitbox, outer: &DurHitbox) -> f64 {
+    let duration = inner.vel.duration.min(outer.vel.duration);
+    if !inner
+        .bounding_box_for(duration)
+        .overlaps(&outer.bounding_box_for(duration))
+    {
+        return f64::INFINITY;
+    }
+    if !is_axis_aligned(inner) || !is_axis_aligned(outer) {
+        return advancement_time(inner, outer, duration, |inner, outer| {
+            -containment_margin(inner, outer)
+        });
+    }
+    let intervals: Vec<Intervals> = containment_quadratics(inner, outer)
+        .into_iter()
+        .map(|(a, b, c)| non_negative_intervals(a, b, c))
+        .collect();
+
+    // the earliest time in every set of intervals is either the present or
+    // the start of one of the intervals
+    let mut candidates: Vec<f64> = intervals
+        .iter()
+        .flat_map(|intervals| intervals.iter().map(|&(start, _)| start))
+        .filter(|&start| start > 0.0)
+        .collect();
+    candidates.push(0.0);
+    candidates.sort_by_key(|&time| n64(time));
+    candidates
+        .into_iter()
+        .take_while(|&time| time < duration)
+        .find(|&time| {
+            intervals.iter().all(|intervals| {
+                intervals
+                    .iter()
+                    .any(|&(start, end)| start <= time && time <= end)
+            })
+        })
+        .unwrap_or(f64::INFINITY)
+}
+
+pub fn uncontain_time(inner: &DurHitbox, outer: &DurHitbox, padding: f64) -> f64 {
+    let duration = inner.vel.duration.min(outer.vel.duration);
+    let outer = outer.padded(padding);
+    if !is_axis_aligned(inner) || !is_axis_aligned(&outer) {
+        return advancement_time(inner, &outer, duration, containment_margin);
+    }
+    let result = containment_quadratics(inner, &outer)
+        .into_iter()
+        .map(|(a, b, c)| {
+            non_negative_intervals(a, b, c)
+                .iter()
+                .find(|&&(start, end)| start <= 0.0 && 0.0 <= end)
+                .map_or(0.0, |&(_, end)| end)
+        })
+        .fold(f64::INFINITY, f64::min);
+    if result >= duration {
+        f64::INFINITY
+    } else {
+        result
+    }
+}
+
+// returns the time until `hitbox` starts to extend outside of `bounds`
+pub fn exit_time(hitbox: &DurHitbox, bounds: &PlacedShape) -> f64 {
+    let bounds = DurHitbox::new(*bounds);
+    advancement_time(hitbox, &bounds, hitbox.vel.duration, |hitbox, bounds| {
+        Card::values()
+            .iter()
+            .map(|&card| bounds.edge(card) - hitbox.edge(card))
+            .fold(f64::INFINITY, f64::min)
+    })
+}
+
+// Returns the first time that `measure` of the two hitboxes drops to zero, or
+// infinity if that does not happen within `duration` or within
+// `MAX_ADVANCEMENT_STEPS` steps. `measure` must be a distance that changes no
+// faster than the points of the hitboxes move, so stepping forward by
+// `measure / speed` never skips past a root.
+fn advancement_time<F>(a: &DurHitbox, b: &DurHitbox, duration: f64, measure: F) -> f64
+where
+    F: Fn(&PlacedShape, &PlacedShape) -> f64,
+{
+    let linear_speed = (a.vel.value - b.vel.value).len();
+    let tolerance =
+        ADVANCEMENT_TOLERANCE * (1.0 + a.value.shape.circumradius() + b.value.shape.circumradius());
+    let mut time = 0.0;
+    for _ in 0..MAX_ADVANCEMENT_STEPS {
+        if time >= duration.min(core::HIGH_TIME) {
+            return f64::INFINITY;
+        }
+        let (a_now, b_now) = (a.advanced(time), b.advanced(time));
+        let gap = measure(&a_now.value, &b_now.value);
+        if gap <= tolerance {
+            return time;
+        }
+        // a first guess at the step, used to bound the growth of the hitboxes
+        // over the step
+        let speed = |horizon: f64| {
+            let horizon = horizon.min(duration - time).min(core::HIGH_TIME * 0.5);
+            linear_speed + a_now.turn_speed(horizon) + b_now.turn_speed(horizon)
+        };
+        let speed = speed(gap / speed(0.0));
+        if speed <= 0.0 {
+            return f64::INFINITY;
+        }
+        time += gap / speed;
+    }
+    // Give up on a root that is approached too slowly. The hitboxes are still
+    // apart, so reporting `time` would be a spurious event; instead nothing is
+    // reported, and the pair is solved again when either hitbox reiterates at
+    // the end of its duration.
+    f64::INFINITY
+}
+
+const ADVANCEMENT_TOLERANCE: f64 = 1e-9;
+const MAX_ADVANCEMENT_STEPS: u32 = 10_000;
+
+// returns whether the containment of `hitbox` can be solved with
+// `containment_quadratics`
+fn is_axis_aligned(hitbox: &DurHitbox) -> bool {
+    if hitbox.vel.orbit.is_some() {
+        return false;
+    }
+    match hitbox.value.kind() {
+        ShapeKind::Rect => true,
+        ShapeKind::Circle => !is_elliptical(hitbox),
+        ShapeKind::OrientedRect | ShapeKind::Polygon | ShapeKind::HalfPlane => false,
+    }
+}
+
+// returns how far `inner` is inside of `outer`, which is negative if it
+// extends outside of it
+fn containment_margin(inner: &PlacedShape, outer: &PlacedShape) -> f64 {
+    if inner.kind() == ShapeKind::HalfPlane {
+        // a half-plane never fits inside of another shape
+        return f64::NEG_INFINITY;
+    }
+    match outer.kind() {
+        // a convex shape contains another exactly when it extends at least as
+        // far in every direction
+        ShapeKind::Circle if outer.shape.is_ellipse() || inner.shape.is_ellipse() => {
+            min_over_dirs(|dir| outer.support(dir) - inner.support(dir)).1
+        }
+        ShapeKind::Circle => {
+            let reach = match inner.kind() {
+                ShapeKind::Circle => inner.pos.dist(&outer.pos) + inner.dims().x * 0.5,
+                _ => {
+                    inner
+                        .corners()
+                        .iter()
+                        .map(|corner| corner.dist(&outer.pos))
+                        .fold(0.0, f64::max)
+                        + inner.shape.polygon_padding()
+                }
+            };
+            outer.dims().x * 0.5 - reach
+        }
+        // a convex shape contains another exactly when it does so along each
+        // of its edge normals
+        _ => outer
+            .edge_normals()
+            .into_iter()
+            .map(|normal| outer.support(normal) - inner.support(normal))
+            .fold(f64::INFINITY, f64::min),
+    }
+}
+
+// up to two disjoint closed intervals of time, in increasing order
+type Intervals = Vec<(f64, f64)>;
+
+// returns quadratic polynomials `(a, b, c)` in time that are all non-negative
+// exactly when `inner` is contained in `outer`
+fn containment_quadratics(inner: &DurHitbox, outer: &DurHitbox) -> Vec<(f64, f64, f64)> {
+    match (outer.value.kind(), inner.value.kind()) {
+        (ShapeKind::OrientedRect, _)
+        | (_, ShapeKind::OrientedRect)
+        | (ShapeKind::Polygon, _)
+        | (_, ShapeKind::Polygon)
+        | (ShapeKind::HalfPlane, _)
+        | (_, ShapeKind::HalfPlane) => {
+            unreachable!("rotated, polygon and half-plane shapes use containment_margin")
+        }
+        // a rect contains a circle exactly when it contains its bounding box
+        (ShapeKind::Rect, _) => Card::values()
+            .iter()
+            .map(|&card| {
+                let gap = outer.value.edge(card) - inner.value.edge(card);
+                let gap_vel = outer.vel.edge(card) - inner.vel.edge(card);
+                (0.0, gap_vel, gap)
+            })
+            .collect(),
+        (ShapeKind::Circle, ShapeKind::Circle) => {
+            let rad = (outer.value.dims().x - inner.value.dims().x) * 0.5;
+            let rad_vel = (outer.vel.resize.x - inner.vel.resize.x) * 0.5;
+            let dist = inner.value.pos - outer.value.pos;
+            let dist_vel = inner.vel.value - outer.vel.value;
+            vec![
+                (0.0, rad_vel, rad),
+                point_in_circle_quadratic(rad, rad_vel, dist, dist_vel),
+            ]
+        }
+        // a circle contains a rect exactly when it contains its corners
+        (ShapeKind::Circle, ShapeKind::Rect) => {
+            let rad = outer.value.dims().x * 0.5;
+            let rad_vel = outer.vel.resize.x * 0.5;
+            [Ordering::Less, Ordering::Greater]
+                .iter()
+                .flat_map(|&x| {
+                    [Ordering::Less, Ordering::Greater]
+                        .iter()
+                        .map(move |&y| Sector::new(x, y))
+                })
+                .map(|sector| {
+                    let dist = inner.value.corner(sector) - outer.value.pos;
+                    let dist_vel = inner.vel.corner(sector) - outer.vel.value;
+                    point_in_circle_quadratic(rad, rad_vel, dist, dist_vel)
+                })
+                .collect()
+        }
+    }
+}
+
+// returns the quadratic that is non-negative while a point at `dist` from the
+// center of a circle with radius `rad` is inside of that circle
+fn point_in_circle_quadratic(
+    rad: f64,
+    rad_vel: f64,
+    dist: Vec2,
+    dist_vel: Vec2,
+) -> (f64, f64, f64) {
+    (
+        rad_vel * rad_vel - dist_vel.len_sq(),
+        2.0 * (rad * rad_vel - dist * dist_vel),
+        rad * rad - dist.len_sq(),
+    )
+}
+
+// returns the times at which `a t^2 + b t + c` is non-negative
+fn non_negative_intervals(a: f64, b: f64, c: f64) -> Intervals {
+    let all = (f64::NEG_INFINITY, f64::INFINITY);
+    if a == 0.0 {
+        return if b > 0.0 {
+            vec![(-c / b, f64::INFINITY)]
+        } else if b < 0.0 {
+            vec![(f64::NEG_INFINITY, -c / b)]
+        } else if c >= 0.0 {
+            vec![all]
+        } else {
+            vec![]
+        };
+    }
+    let determinant = b * b - a * c * 4.0;
+    if determinant <= 0.0 {
+        return if a > 0.0 { vec![all] } else { vec![] };
+    }
+    // q is never zero, since the determinant is positive
+    let q = -0.5 * (b + b.signum() * determinant.sqrt());
+    let (root_1, root_2) = (q / a, c / q);
+    let (root_1, root_2) = (root_1.min(root_2), root_1.max(root_2));
+    if a > 0.0 {
+        vec![(f64::NEG_INFINITY, root_1), (root_2, f64::INFINITY)]
+    } else {
+        vec![(root_1, root_2)]
+    }
+}
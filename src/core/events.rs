@@ -12,29 +12,44 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::core::{HbId, HIGH_TIME};
+use crate::core::{HbSlot, TileCoord, HIGH_TIME};
 use crate::float::n64;
 use crate::util::{OneOrTwo, TightSet};
 use std::cmp::Ordering;
-use std::collections::BTreeMap;
 use std::f64;
 use std::hash::{Hash, Hasher};
 
 // This module contains Collider events that are queued to occur at given
 // simulation times. The EventManager can queue and cancel these events.
+//
+// Events are stored in an indexed d-ary heap. Each EventKey doubles as a handle
+// that tracks the position of its event within the heap, so that events can be
+// cancelled in logarithmic time without any tree rebalancing or hashing.
 
 const PAIR_BASE: u64 = 0x8000_0000_0000_0000;
 
+// number of children of each node in the heap
+const ARITY: usize = 4;
+
 #[derive(Copy, Clone)]
 pub struct EventKey {
     time: f64,
     index: u64,
+    handle: usize,
 }
 
 impl EventKey {
-    fn time(&self) -> f64 {
+    pub fn time(&self) -> f64 {
         self.time
     }
+
+    // returns the same key, with `offset` subtracted from its time
+    pub fn rebased(self, offset: f64) -> EventKey {
+        EventKey {
+            time: self.time - offset,
+            ..self
+        }
+    }
 }
 
 impl PartialEq for EventKey {
@@ -68,46 +83,72 @@ impl Ord for EventKey {
 }
 
 pub trait EventKeysMap {
-    fn event_keys_mut(&mut self, id: HbId) -> &mut TightSet<EventKey>;
+    fn event_keys_mut(&mut self, slot: HbSlot) -> &mut TightSet<EventKey>;
 }
 
 #[derive(Copy, Clone)]
 pub enum InternalEvent {
     #[cfg(debug_assertions)]
-    PanicSmallHitbox(HbId),
+    PanicSmallHitbox(HbSlot),
     #[cfg(debug_assertions)]
-    PanicDurationPassed(HbId),
-    Reiterate(HbId),
-    Collide(HbId, HbId),
-    Separate(HbId, HbId),
+    PanicDurationPassed(HbSlot),
+    Reiterate(HbSlot),
+    Waypoint(HbSlot),
+    OutOfBounds(HbSlot),
+    TileCollide(HbSlot, TileCoord),
+    TileSeparate(HbSlot, TileCoord),
+    Collide(HbSlot, HbSlot),
+    Separate(HbSlot, HbSlot),
+    Enter(HbSlot, HbSlot),
+    Exit(HbSlot, HbSlot),
+    Contain(HbSlot, HbSlot),
+    Uncontain(HbSlot, HbSlot),
 }
 
 impl InternalEvent {
-    fn other_id(self, id: HbId) -> Option<HbId> {
-        self.involved_hitbox_ids().other_id(id)
+    pub fn other_slot(self, slot: HbSlot) -> Option<HbSlot> {
+        self.involved_slots().other_id(slot)
     }
 
-    fn involved_hitbox_ids(self) -> OneOrTwo<HbId> {
+    fn involved_slots(self) -> OneOrTwo<HbSlot> {
         match self {
             #[cfg(debug_assertions)]
-            InternalEvent::PanicSmallHitbox(id) | InternalEvent::PanicDurationPassed(id) => {
-                OneOrTwo::One(id)
+            InternalEvent::PanicSmallHitbox(slot) | InternalEvent::PanicDurationPassed(slot) => {
+                OneOrTwo::One(slot)
             }
-            InternalEvent::Reiterate(id) => OneOrTwo::One(id),
-            InternalEvent::Collide(a, b) | InternalEvent::Separate(a, b) => OneOrTwo::Two(a, b),
+            InternalEvent::Reiterate(slot)
+            | InternalEvent::Waypoint(slot)
+            | InternalEvent::OutOfBounds(slot)
+            | InternalEvent::TileCollide(slot, _)
+            | InternalEvent::TileSeparate(slot, _) => OneOrTwo::One(slot),
+            InternalEvent::Collide(a, b)
+            | InternalEvent::Separate(a, b)
+            | InternalEvent::Enter(a, b)
+            | InternalEvent::Exit(a, b)
+            | InternalEvent::Contain(a, b)
+            | InternalEvent::Uncontain(a, b) => OneOrTwo::Two(a, b),
         }
     }
 }
 
+struct HeapEntry {
+    key: EventKey,
+    event: InternalEvent,
+}
+
 pub struct EventManager {
-    events: BTreeMap<EventKey, InternalEvent>,
+    heap: Vec<HeapEntry>,
+    positions: Vec<usize>,
+    free_handles: Vec<usize>,
     next_event_index: u64,
 }
 
 impl EventManager {
     pub fn new() -> EventManager {
         EventManager {
-            events: BTreeMap::new(),
+            heap: Vec::new(),
+            positions: Vec::new(),
+            free_handles: Vec::new(),
             next_event_index: 0,
         }
     }
@@ -119,7 +160,7 @@ impl EventManager {
         key_set: &mut TightSet<EventKey>,
     ) {
         if let Some(key) = self.new_event_key(time, false) {
-            assert!(self.events.insert(key, event).is_none());
+            self.insert(key, event);
             assert!(key_set.insert(key));
         }
     }
@@ -132,7 +173,7 @@ impl EventManager {
         second_key_set: &mut TightSet<EventKey>,
     ) {
         if let Some(key) = self.new_event_key(time, true) {
-            assert!(self.events.insert(key, event).is_none());
+            self.insert(key, event);
             assert!(first_key_set.insert(key));
             assert!(second_key_set.insert(key));
         }
@@ -140,14 +181,14 @@ impl EventManager {
 
     pub fn clear_related_events<M: EventKeysMap>(
         &mut self,
-        id: HbId,
+        slot: HbSlot,
         key_set: &mut TightSet<EventKey>,
         map: &mut M,
     ) {
         for key in key_set.iter() {
-            let event = self.events.remove(key).unwrap();
-            if let Some(other_id) = event.other_id(id) {
-                assert!(map.event_keys_mut(other_id).remove(key));
+            let event = self.remove(key);
+            if let Some(other_slot) = event.other_slot(slot) {
+                assert!(map.event_keys_mut(other_slot).remove(key));
             }
         }
         key_set.clear();
@@ -163,11 +204,34 @@ impl EventManager {
             if for_pair {
                 index += PAIR_BASE;
             }
-            let result = EventKey { time, index };
+            let handle = self.free_handles.pop().unwrap_or_else(|| {
+                self.positions.push(0);
+                self.positions.len() - 1
+            });
+            let result = EventKey {
+                time,
+                index,
+                handle,
+            };
             Some(result)
         }
     }
 
+    // subtracts `offset` from the time of every queued event; the keys held
+    // by hitboxes must be rebased as well
+    pub fn rebase_time(&mut self, offset: f64) {
+        for entry in &mut self.heap {
+            entry.key = entry.key.rebased(offset);
+        }
+        // rounding may make distinct times equal, leaving their order up to
+        // their indices, so the heap is rebuilt
+        if self.heap.len() > 1 {
+            for pos in (0..=(self.heap.len() - 2) / ARITY).rev() {
+                self.sift_down(pos);
+            }
+        }
+    }
+
     pub fn peek_time(&self) -> f64 {
         self.peek_key().map_or(f64::INFINITY, |key| key.time())
     }
@@ -175,9 +239,9 @@ impl EventManager {
     pub fn next<M: EventKeysMap>(&mut self, time: f64, map: &mut M) -> Option<InternalEvent> {
         if let Some(key) = self.peek_key() {
             if key.time() == time {
-                let event = self.events.remove(&key).unwrap();
-                for id in event.involved_hitbox_ids().iter() {
-                    assert!(map.event_keys_mut(id).remove(&key));
+                let event = self.remove(&key);
+                for slot in event.involved_slots().iter() {
+                    assert!(map.event_keys_mut(slot).remove(&key));
                 }
                 Some(event)
             } else {
@@ -188,7 +252,159 @@ impl EventManager {
         }
     }
 
+    // like `next`, but only processes the next event if it is a Reiterate
+    // event, returning its hitbox
+    pub fn next_reiterate<M: EventKeysMap>(&mut self, time: f64, map: &mut M) -> Option<HbSlot> {
+        match self.heap.first() {
+            Some(&HeapEntry {
+                key,
+                event: InternalEvent::Reiterate(slot),
+            }) if key.time() == time => {
+                self.next(time, map);
+                Some(slot)
+            }
+            _ => None,
+        }
+    }
+
+    // returns the event that is queued with `key`
+    pub fn get(&self, key: &EventKey) -> InternalEvent {
+        let entry = &self.heap[self.positions[key.handle]];
+        assert!(entry.key == *key, "event key not found");
+        entry.event
+    }
+
     fn peek_key(&self) -> Option<EventKey> {
-        self.events.keys().next().map(|&key| key)
+        self.heap.first().map(|entry| entry.key)
+    }
+
+    fn insert(&mut self, key: EventKey, event: InternalEvent) {
+        self.heap.push(HeapEntry { key, event });
+        let pos = self.heap.len() - 1;
+        self.positions[key.handle] = pos;
+        self.sift_up(pos);
+    }
+
+    fn remove(&mut self, key: &EventKey) -> InternalEvent {
+        let pos = self.positions[key.handle];
+        assert!(self.heap[pos].key == *key, "event key not found");
+        let entry = self.heap.swap_remove(pos);
+        self.free_handles.push(key.handle);
+        if pos < self.heap.len() {
+            self.positions[self.heap[pos].key.handle] = pos;
+            if pos > 0 && self.heap[pos].key < self.heap[(pos - 1) / ARITY].key {
+                self.sift_up(pos);
+            } else {
+                self.sift_down(pos);
+            }
+        }
+        entry.event
+    }
+
+    fn sift_up(&mut self, mut pos: usize) {
+        while pos > 0 {
+            let parent = (pos - 1) / ARITY;
+            if self.heap[pos].key >= self.heap[parent].key {
+                break;
+            }
+            self.swap(pos, parent);
+            pos = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut pos: usize) {
+        loop {
+            let first_child = pos * ARITY + 1;
+            let end = (first_child + ARITY).min(self.heap.len());
+            let min_child = (first_child..end).min_by_key(|&child| self.heap[child].key);
+            match min_child {
+                Some(child) if self.heap[child].key < self.heap[pos].key => {
+                    self.swap(pos, child);
+                    pos = child;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn swap(&mut self, pos_1: usize, pos_2: usize) {
+        self.heap.swap(pos_1, pos_2);
+        self.positions[self.heap[pos_1].key.handle] = pos_1;
+        self.positions[self.heap[pos_2].key.handle] = pos_2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoKeys;
+
+    impl EventKeysMap for NoKeys {
+        fn event_keys_mut(&mut self, _slot: HbSlot) -> &mut TightSet<EventKey> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_event_order() {
+        let mut events = EventManager::new();
+        let mut key_sets: Vec<TightSet<EventKey>> = (0..40).map(|_| TightSet::new()).collect();
+        for (slot, key_set) in key_sets.iter_mut().enumerate() {
+            let time = ((slot * 17) % 10) as f64;
+            events.add_solitaire_event(time, InternalEvent::Reiterate(slot), key_set);
+        }
+        events.add_solitaire_event(HIGH_TIME, InternalEvent::Reiterate(0), &mut TightSet::new());
+        for slot in (0..40).filter(|slot| slot % 3 == 0) {
+            events.clear_related_events(slot, &mut key_sets[slot], &mut NoKeys);
+        }
+
+        // events are ordered by time, then by the order they were added in
+        let mut expected: Vec<(usize, usize)> = (0..40)
+            .filter(|slot| slot % 3 != 0)
+            .map(|slot| ((slot * 17) % 10, slot))
+            .collect();
+        expected.sort();
+        for (time, slot) in expected {
+            let time = time as f64;
+            assert_eq!(events.peek_time(), time);
+            let mut map = SingleKeys(&mut key_sets[slot]);
+            match events.next(time, &mut map) {
+                Some(InternalEvent::Reiterate(event_slot)) => assert_eq!(event_slot, slot),
+                _ => panic!("unexpected event"),
+            }
+        }
+        assert_eq!(events.peek_time(), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_rebase_time() {
+        let mut events = EventManager::new();
+        let mut key_sets: Vec<TightSet<EventKey>> = (0..40).map(|_| TightSet::new()).collect();
+        for (slot, key_set) in key_sets.iter_mut().enumerate() {
+            let time = (40 - slot) as f64;
+            events.add_solitaire_event(time, InternalEvent::Reiterate(slot), key_set);
+        }
+
+        // every time rounds to 1e20, so the events are ordered by the order
+        // they were added in
+        events.rebase_time(-1e20);
+        for (slot, key_set) in key_sets.iter_mut().enumerate() {
+            assert_eq!(events.peek_time(), 1e20);
+            let mut map = SingleKeys(key_set);
+            match events.next(1e20, &mut map) {
+                Some(InternalEvent::Reiterate(event_slot)) => assert_eq!(event_slot, slot),
+                _ => panic!("unexpected event"),
+            }
+        }
+        assert_eq!(events.peek_time(), f64::INFINITY);
+    }
+
+    struct SingleKeys<'a>(&'a mut TightSet<EventKey>);
+
+    impl EventKeysMap for SingleKeys<'_> {
+        fn event_keys_mut(&mut self, _slot: HbSlot) -> &mut TightSet<EventKey> {
+            self.0
+        }
     }
 }
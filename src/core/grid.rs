@@ -13,8 +13,8 @@
 // limitations under the License.
 
 use crate::core::dur_hitbox::DurHitbox;
-use crate::core::{HbGroup, HbId, Hitbox};
-use crate::geom::shape::{PlacedBounds, PlacedShape};
+use crate::core::{HbGroup, HbSlot, Hitbox};
+use crate::geom::shape::{PlacedBounds, PlacedShape, ShapeKind};
 use crate::index_rect::IndexRect;
 use crate::util::TightSet;
 use fnv::{FnvHashMap, FnvHashSet};
@@ -22,133 +22,396 @@ use std::cmp;
 use std::collections::hash_map;
 use std::f64;
 
-// Grid is a sparse 2D grid implemented as a HashMap. This is used as the
-// pruning method to decide which hitboxes to check for collisions.
+// Grid is a hierarchical 2D grid that is used as the pruning method to decide
+// which hitboxes to check for collisions. By default the cells are stored
+// sparsely in a HashMap, but a grid with fixed bounds stores them in flat
+// arrays instead, which avoids hashing.
+//
+// The grid has several levels, where the cells of each level are twice as wide
+// as those of the level below it. Each hitbox is filed at the lowest level
+// whose cells are at least as wide as the hitbox, so that large hitboxes only
+// occupy a few cells, and queries check every level that has any hitboxes.
+//
+// Half-planes are unbounded, so they are kept in a set for each group rather
+// than in any cells. Every query includes them, and a half-plane is a cellmate
+// of every hitbox in the groups it interacts with.
 
-//TODO add unit tests for Grid
+// number of levels in the grid, so the coarsest cells are 2^15 times as wide
+// as the finest
+const LEVEL_COUNT: u8 = 16;
 
 #[derive(PartialEq, Eq, Copy, Clone, Hash)]
 struct GridKey {
     coord: (i32, i32),
     group: HbGroup,
+    level: u8,
 }
 
 #[derive(Copy, Clone)]
 struct GridArea {
     rect: IndexRect,
     group: HbGroup,
+    level: u8,
 }
 
 impl GridArea {
     fn contains(&self, key: GridKey) -> bool {
-        self.group == key.group && self.rect.contains(key.coord)
+        self.group == key.group && self.level == key.level && self.rect.contains(key.coord)
+    }
+
+    fn keys(self) -> impl Iterator<Item = GridKey> {
+        self.rect.iter().map(move |coord| GridKey {
+            coord,
+            group: self.group,
+            level: self.level,
+        })
+    }
+}
+
+enum Cells {
+    Sparse(FnvHashMap<GridKey, TightSet<HbSlot>>),
+    Dense(DenseCells),
+}
+
+// the cells of a grid with fixed bounds, where hitboxes that are not entirely
+// inside the bounds are kept in an overflow set instead
+struct DenseCells {
+    // the cells covered at each level, and the index of the first of them in
+    // each layer
+    ranges: Vec<(IndexRect, usize)>,
+    cell_count: usize,
+    layers: Vec<DenseLayer>,
+}
+
+struct DenseLayer {
+    group: HbGroup,
+    cells: Vec<Vec<HbSlot>>,
+    overflow: TightSet<HbSlot>,
+}
+
+impl DenseCells {
+    fn layer(&self, group: HbGroup) -> Option<&DenseLayer> {
+        self.layers.iter().find(|layer| layer.group == group)
+    }
+
+    fn layer_mut(&mut self, group: HbGroup) -> &mut DenseLayer {
+        match self.layers.iter().position(|layer| layer.group == group) {
+            Some(index) => &mut self.layers[index],
+            None => {
+                self.layers.push(DenseLayer {
+                    group,
+                    cells: vec![Vec::new(); self.cell_count],
+                    overflow: TightSet::new(),
+                });
+                self.layers.last_mut().unwrap()
+            }
+        }
+    }
+
+    fn covers(&self, area: &GridArea) -> bool {
+        self.ranges[area.level as usize].0.contains_rect(area.rect)
+    }
+
+    fn index(&self, key: GridKey) -> usize {
+        let (range, start) = self.ranges[key.level as usize];
+        start + range.offset(key.coord)
+    }
+
+    fn update(
+        &mut self,
+        hitbox_slot: HbSlot,
+        old_area: Option<GridArea>,
+        new_area: Option<GridArea>,
+    ) {
+        let old_area = match old_area {
+            Some(old_area) if !self.covers(&old_area) => {
+                let success = self.layer_mut(old_area.group).overflow.remove(&hitbox_slot);
+                assert!(success);
+                None
+            }
+            old_area => old_area,
+        };
+        let new_area = match new_area {
+            Some(new_area) if !self.covers(&new_area) => {
+                let success = self.layer_mut(new_area.group).overflow.insert(hitbox_slot);
+                assert!(success);
+                None
+            }
+            new_area => new_area,
+        };
+        if let Some(old_area) = old_area {
+            for key in old_area.keys() {
+                if !matches!(new_area, Some(new_area) if new_area.contains(key)) {
+                    let index = self.index(key);
+                    let cell = &mut self.layer_mut(key.group).cells[index];
+                    let pos = cell.iter().position(|&slot| slot == hitbox_slot).unwrap();
+                    cell.swap_remove(pos);
+                }
+            }
+        }
+        if let Some(new_area) = new_area {
+            for key in new_area.keys() {
+                if !matches!(old_area, Some(old_area) if old_area.contains(key)) {
+                    let index = self.index(key);
+                    let cell = &mut self.layer_mut(key.group).cells[index];
+                    debug_assert!(!cell.contains(&hitbox_slot));
+                    cell.push(hitbox_slot);
+                }
+            }
+        }
     }
 }
 
 pub struct Grid {
-    map: FnvHashMap<GridKey, TightSet<HbId>>,
+    cells: Cells,
     cell_width: f64,
+    level_counts: [usize; LEVEL_COUNT as usize],
+    half_planes: FnvHashMap<HbGroup, TightSet<HbSlot>>,
 }
 
 impl Grid {
     pub fn new(cell_width: f64) -> Grid {
         Grid {
-            map: FnvHashMap::default(),
+            cells: Cells::Sparse(FnvHashMap::default()),
             cell_width,
+            level_counts: [0; LEVEL_COUNT as usize],
+            half_planes: FnvHashMap::default(),
         }
     }
 
+    pub fn with_bounds(cell_width: f64, bounds: &PlacedShape) -> Grid {
+        let mut grid = Grid::new(cell_width);
+        let mut cell_count = 0;
+        let ranges = (0..LEVEL_COUNT)
+            .map(|level| {
+                let range = grid.index_bounds(bounds, level);
+                cell_count += range.len();
+                (range, cell_count - range.len())
+            })
+            .collect();
+        grid.cells = Cells::Dense(DenseCells {
+            ranges,
+            cell_count,
+            layers: Vec::new(),
+        });
+        grid
+    }
+
+    pub fn cell_width(&self) -> f64 {
+        self.cell_width
+    }
+
     pub fn cell_period(&self, hitbox: &Hitbox, has_group: bool) -> f64 {
         if has_group {
-            let speed = hitbox.vel.max_edge();
+            let mut speed = hitbox.vel.max_edge();
+            if hitbox.vel.angular != 0.0 {
+                speed += hitbox.vel.angular.abs() * hitbox.value.shape.circumradius();
+            }
+            if let Some(orbit) = hitbox.vel.orbit {
+                speed += orbit.speed(hitbox.value.pos);
+            }
             if speed <= 0.0 {
                 f64::INFINITY
             } else {
-                self.cell_width / speed
+                self.level_width(self.level(&hitbox.value)) / speed
             }
         } else {
             f64::INFINITY
         }
     }
 
-    pub fn shape_cellmates(&self, shape: &PlacedShape, groups: &[HbGroup]) -> FnvHashSet<HbId> {
-        let bounds = self.index_bounds(shape);
-        self.overlapping_ids(None, bounds, groups)
+    pub fn shape_cellmates(&self, shape: &PlacedShape, groups: &[HbGroup]) -> FnvHashSet<HbSlot> {
+        let mut result = FnvHashSet::default();
+        if shape.kind() == ShapeKind::HalfPlane {
+            self.fill_all_ids(groups, &mut result);
+        } else {
+            self.fill_overlapping_ids(None, shape, groups, &mut result);
+        }
+        result
     }
 
     pub fn update_hitbox(
         &mut self,
-        hitbox_id: HbId,
+        hitbox_slot: HbSlot,
         group: HbGroup,
         old_hitbox: Option<&DurHitbox>,
         new_hitbox: Option<&DurHitbox>,
         groups: &[HbGroup],
-    ) -> Option<FnvHashSet<HbId>> {
+    ) -> Option<FnvHashSet<HbSlot>> {
         assert!(new_hitbox.is_some() || groups.is_empty());
-        let old_area = old_hitbox.map(|old_hitbox| self.grid_area(old_hitbox, group));
-        let new_area = new_hitbox.map(|new_hitbox| self.grid_area(new_hitbox, group));
-        self.update_area(hitbox_id, old_area, new_area);
-        new_area.map(|new_area| self.overlapping_ids(Some(hitbox_id), new_area.rect, groups))
+        if old_hitbox.or(new_hitbox).unwrap().value.kind() == ShapeKind::HalfPlane {
+            // a half-plane never moves, so it is only ever added or removed
+            let half_planes = self.half_planes.entry(group).or_insert_with(TightSet::new);
+            match (old_hitbox, new_hitbox) {
+                (None, Some(_)) => assert!(half_planes.insert(hitbox_slot)),
+                (Some(_), None) => assert!(half_planes.remove(&hitbox_slot)),
+                _ => (),
+            }
+        } else {
+            let old_area = old_hitbox.map(|old_hitbox| self.grid_area(old_hitbox, group));
+            let new_area = new_hitbox.map(|new_hitbox| self.grid_area(new_hitbox, group));
+            self.update_area(hitbox_slot, old_area, new_area);
+        }
+        new_hitbox.map(|new_hitbox| {
+            let mut result = FnvHashSet::default();
+            self.fill_cellmates(hitbox_slot, new_hitbox, groups, &mut result);
+            result
+        })
+    }
+
+    // fills `result` with the hitboxes in the given groups that share a cell
+    // with `hitbox`, which must already be in the grid
+    pub fn fill_cellmates(
+        &self,
+        hitbox_slot: HbSlot,
+        hitbox: &DurHitbox,
+        groups: &[HbGroup],
+        result: &mut FnvHashSet<HbSlot>,
+    ) {
+        if hitbox.value.kind() == ShapeKind::HalfPlane {
+            self.fill_all_ids(groups, result);
+        } else {
+            let bounds = hitbox.bounding_box();
+            self.fill_overlapping_ids(Some(hitbox_slot), &bounds, groups, result);
+        }
+    }
+
+    // fills `result` with every hitbox in the given groups, other than
+    // half-planes
+    fn fill_all_ids(&self, groups: &[HbGroup], result: &mut FnvHashSet<HbSlot>) {
+        match self.cells {
+            Cells::Sparse(ref map) => {
+                for (key, slots) in map.iter() {
+                    if groups.contains(&key.group) {
+                        result.extend(slots.iter().cloned());
+                    }
+                }
+            }
+            Cells::Dense(ref dense) => {
+                for layer in groups.iter().filter_map(|&group| dense.layer(group)) {
+                    for cell in &layer.cells {
+                        result.extend(cell.iter().cloned());
+                    }
+                    result.extend(layer.overflow.iter().cloned());
+                }
+            }
+        }
+    }
+
+    // returns the level that a hitbox with the given shape is filed at
+    fn level(&self, shape: &PlacedShape) -> u8 {
+        let dims = shape.bounds_dims();
+        let extent = dims.x.max(dims.y);
+        (0..LEVEL_COUNT - 1)
+            .find(|&level| self.level_width(level) >= extent)
+            .unwrap_or(LEVEL_COUNT - 1)
+    }
+
+    fn level_width(&self, level: u8) -> f64 {
+        self.cell_width * (1u32 << level) as f64
+    }
+
+    fn occupied_levels(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..LEVEL_COUNT).filter(move |&level| self.level_counts[level as usize] > 0)
     }
 
     fn grid_area(&self, hitbox: &DurHitbox, group: HbGroup) -> GridArea {
+        let level = self.level(&hitbox.value);
         GridArea {
-            rect: self.index_bounds(&hitbox.bounding_box()),
+            rect: self.index_bounds(&hitbox.bounding_box(), level),
             group,
+            level,
         }
     }
 
-    fn index_bounds(&self, bounds: &PlacedShape) -> IndexRect {
-        let start_x = (bounds.min_x() / self.cell_width).floor() as i32;
-        let start_y = (bounds.min_y() / self.cell_width).floor() as i32;
-        let end_x = cmp::max(
-            (bounds.max_x() / self.cell_width).ceil() as i32,
-            start_x + 1,
-        );
-        let end_y = cmp::max(
-            (bounds.max_y() / self.cell_width).ceil() as i32,
-            start_y + 1,
-        );
+    fn index_bounds(&self, bounds: &PlacedShape, level: u8) -> IndexRect {
+        let cell_width = self.level_width(level);
+        let start_x = (bounds.min_x() / cell_width).floor() as i32;
+        let start_y = (bounds.min_y() / cell_width).floor() as i32;
+        let end_x = cmp::max((bounds.max_x() / cell_width).ceil() as i32, start_x + 1);
+        let end_y = cmp::max((bounds.max_y() / cell_width).ceil() as i32, start_y + 1);
         IndexRect::new((start_x, start_y), (end_x, end_y))
     }
 
-    fn overlapping_ids(
+    fn fill_overlapping_ids(
         &self,
-        hitbox_id: Option<HbId>,
-        rect: IndexRect,
+        hitbox_slot: Option<HbSlot>,
+        bounds: &PlacedShape,
         groups: &[HbGroup],
-    ) -> FnvHashSet<HbId> {
-        let mut result = FnvHashSet::default();
-        for &group in groups {
-            for coord in rect.iter() {
-                let key = GridKey { coord, group };
-                if let Some(other_ids) = self.map.get(&key) {
-                    for &other_id in other_ids.iter() {
-                        if Some(other_id) != hitbox_id {
-                            result.insert(other_id);
+        result: &mut FnvHashSet<HbSlot>,
+    ) {
+        let mut insert = |other_slot: HbSlot| {
+            if Some(other_slot) != hitbox_slot {
+                result.insert(other_slot);
+            }
+        };
+        match self.cells {
+            Cells::Sparse(ref map) => {
+                for level in self.occupied_levels() {
+                    let rect = self.index_bounds(bounds, level);
+                    for &group in groups {
+                        let area = GridArea { rect, group, level };
+                        for key in area.keys() {
+                            if let Some(other_slots) = map.get(&key) {
+                                other_slots
+                                    .iter()
+                                    .for_each(|&other_slot| insert(other_slot));
+                            }
+                        }
+                    }
+                }
+            }
+            Cells::Dense(ref dense) => {
+                for layer in groups.iter().filter_map(|&group| dense.layer(group)) {
+                    for level in self.occupied_levels() {
+                        let (range, start) = dense.ranges[level as usize];
+                        let rect = match self.index_bounds(bounds, level).intersect(range) {
+                            Some(rect) => rect,
+                            None => continue,
+                        };
+                        for coord in rect.iter() {
+                            let cell = &layer.cells[start + range.offset(coord)];
+                            cell.iter().for_each(|&other_slot| insert(other_slot));
                         }
                     }
+                    layer
+                        .overflow
+                        .iter()
+                        .for_each(|&other_slot| insert(other_slot));
                 }
             }
         }
-        result
+        for half_planes in groups
+            .iter()
+            .filter_map(|group| self.half_planes.get(group))
+        {
+            half_planes
+                .iter()
+                .for_each(|&other_slot| insert(other_slot));
+        }
     }
 
     fn update_area(
         &mut self,
-        hitbox_id: HbId,
+        hitbox_slot: HbSlot,
         old_area: Option<GridArea>,
         new_area: Option<GridArea>,
     ) {
         if let Some(old_area) = old_area {
-            for coord in old_area.rect.iter() {
-                let key = GridKey {
-                    coord,
-                    group: old_area.group,
-                };
-                if new_area.map_or(true, |new_area| !new_area.contains(key)) {
-                    if let hash_map::Entry::Occupied(mut entry) = self.map.entry(key) {
-                        let success = entry.get_mut().remove(&hitbox_id);
+            self.level_counts[old_area.level as usize] -= 1;
+        }
+        if let Some(new_area) = new_area {
+            self.level_counts[new_area.level as usize] += 1;
+        }
+        let map = match self.cells {
+            Cells::Sparse(ref mut map) => map,
+            Cells::Dense(ref mut dense) => return dense.update(hitbox_slot, old_area, new_area),
+        };
+        if let Some(old_area) = old_area {
+            for key in old_area.keys() {
+                if !matches!(new_area, Some(new_area) if new_area.contains(key)) {
+                    if let hash_map::Entry::Occupied(mut entry) = map.entry(key) {
+                        let success = entry.get_mut().remove(&hitbox_slot);
                         assert!(success);
                         if entry.get().is_empty() {
                             entry.remove();
@@ -160,17 +423,75 @@ impl Grid {
             }
         }
         if let Some(new_area) = new_area {
-            for coord in new_area.rect.iter() {
-                let key = GridKey {
-                    coord,
-                    group: new_area.group,
-                };
-                if old_area.map_or(true, |old_area| !old_area.contains(key)) {
-                    let other_ids = self.map.entry(key).or_insert_with(|| TightSet::new());
-                    let success = other_ids.insert(hitbox_id);
+            for key in new_area.keys() {
+                if !matches!(old_area, Some(old_area) if old_area.contains(key)) {
+                    let other_slots = map.entry(key).or_insert_with(TightSet::new);
+                    let success = other_slots.insert(hitbox_slot);
                     assert!(success);
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::{v2, Shape};
+
+    #[test]
+    fn test_levels() {
+        let mut grid = Grid::new(2.0);
+        let small = DurHitbox::new(Shape::square(1.0).place(v2(0.5, 0.5)));
+        let large = DurHitbox::new(Shape::rect(v2(100.0, 10.0)).place(v2(150.0, 5.0)));
+        let far = DurHitbox::new(Shape::square(1.0).place(v2(300.0, 0.5)));
+        assert_eq!(grid.level(&small.value), 0);
+        assert_eq!(grid.level(&large.value), 6);
+
+        grid.update_hitbox(0, 0, None, Some(&large), &[]);
+        match grid.cells {
+            Cells::Sparse(ref map) => assert_eq!(map.len(), 2),
+            Cells::Dense(_) => unreachable!(),
+        }
+        let cellmates = grid.update_hitbox(1, 0, None, Some(&small), &[0]).unwrap();
+        assert_eq!(cellmates.into_iter().collect::<Vec<_>>(), vec![0]);
+        let cellmates = grid.update_hitbox(2, 0, None, Some(&far), &[0]).unwrap();
+        assert!(cellmates.is_empty());
+
+        let shape = Shape::square(1.0).place(v2(1.5, 1.5));
+        let mut cellmates: Vec<HbSlot> = grid.shape_cellmates(&shape, &[0]).into_iter().collect();
+        cellmates.sort();
+        assert_eq!(cellmates, vec![0, 1]);
+
+        grid.update_hitbox(0, 0, Some(&large), None, &[]);
+        assert_eq!(grid.level_counts[6], 0);
+        let shape = Shape::square(1.0).place(v2(150.0, 5.0));
+        assert!(grid.shape_cellmates(&shape, &[0]).is_empty());
+    }
+
+    #[test]
+    fn test_dense_overflow() {
+        let bounds = Shape::rect(v2(16.0, 16.0)).place(v2(0.0, 0.0));
+        let mut grid = Grid::with_bounds(2.0, &bounds);
+        let inside = DurHitbox::new(Shape::square(1.0).place(v2(7.0, 7.0)));
+        let outside = DurHitbox::new(Shape::square(1.0).place(v2(7.5, 20.0)));
+        let far = DurHitbox::new(Shape::square(1.0).place(v2(-7.0, -7.0)));
+        grid.update_hitbox(0, 0, None, Some(&inside), &[]);
+        grid.update_hitbox(1, 0, None, Some(&outside), &[]);
+        let cellmates = grid.update_hitbox(2, 0, None, Some(&far), &[0]).unwrap();
+        assert_eq!(cellmates.into_iter().collect::<Vec<_>>(), vec![1]);
+
+        let moved = DurHitbox::new(Shape::square(1.0).place(v2(6.0, 6.0)));
+        grid.update_hitbox(1, 0, Some(&outside), Some(&moved), &[]);
+        match grid.cells {
+            Cells::Dense(ref dense) => assert!(dense.layers[0].overflow.is_empty()),
+            Cells::Sparse(_) => unreachable!(),
+        }
+        let cellmates = grid
+            .update_hitbox(2, 0, Some(&far), Some(&far), &[0])
+            .unwrap();
+        assert!(cellmates.is_empty());
+        let shape = Shape::square(1.0).place(v2(6.5, 6.5));
+        assert_eq!(grid.shape_cellmates(&shape, &[0]).len(), 2);
+    }
+}
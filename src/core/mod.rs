@@ -16,8 +16,11 @@ mod collider;
 mod dur_hitbox;
 mod events;
 mod grid;
+mod slab;
+mod tiles;
 
 pub use self::collider::*;
+pub use self::tiles::{TileCoord, TileLayer};
 
 use std::f64;
 
@@ -30,7 +33,15 @@ const HIGH_TIME: f64 = 1e50;
 /// Type used as a handle for referencing hitboxes in a `Collider` instance.
 pub type HbId = u64;
 
+// Index of a hitbox within the internal storage of a `Collider` instance.
+type HbSlot = usize;
+
 /// Velocity information describing how a hitbox shape is changing over time.
+///
+/// Fields may be added to this struct in later versions, so prefer building
+/// it with the constructors such as `HbVel::moving` and `HbVel::orbiting`,
+/// using struct update syntax with one of them for anything else, as in
+/// `HbVel { angular: 1.0, ..HbVel::still() }`.
 #[derive(PartialEq, Clone, Debug)]
 pub struct HbVel {
     /// The movement velocity of the hitbox.
@@ -45,6 +56,18 @@ pub struct HbVel {
     /// is violated.
     pub resize: Vec2,
 
+    /// The angular velocity of the hitbox in radians per unit time,
+    /// counter-clockwise.
+    ///
+    /// Only an `OrientedRect` may have a non-zero angular velocity.
+    pub angular: f64,
+
+    /// A circular path that the hitbox moves along, if any.
+    ///
+    /// An orbiting hitbox must have a `value` of zero. Its shape keeps its
+    /// orientation as it moves, unless it also has an angular velocity.
+    pub orbit: Option<Orbit>,
+
     /// An upper-bound on the time at which the hitbox will be updated by the
     /// user.
     ///
@@ -65,6 +88,8 @@ impl HbVel {
         HbVel {
             value,
             resize: Vec2::zero(),
+            angular: 0.0,
+            orbit: None,
             end_time: f64::INFINITY,
         }
     }
@@ -75,6 +100,8 @@ impl HbVel {
         HbVel {
             value,
             resize: Vec2::zero(),
+            angular: 0.0,
+            orbit: None,
             end_time,
         }
     }
@@ -85,6 +112,8 @@ impl HbVel {
         HbVel {
             value: Vec2::zero(),
             resize: Vec2::zero(),
+            angular: 0.0,
+            orbit: None,
             end_time: f64::INFINITY,
         }
     }
@@ -95,9 +124,53 @@ impl HbVel {
         HbVel {
             value: Vec2::zero(),
             resize: Vec2::zero(),
+            angular: 0.0,
+            orbit: None,
             end_time,
         }
     }
+
+    /// Creates an `HbVel` that moves along a circle around `center` at
+    /// `angular` radians per unit time, counter-clockwise.
+    ///
+    /// The radius of the circle is the distance of the hitbox from `center`.
+    #[inline]
+    pub fn orbiting(center: Vec2, angular: f64) -> HbVel {
+        HbVel {
+            orbit: Some(Orbit { center, angular }),
+            ..HbVel::still()
+        }
+    }
+
+    // returns true if the hitbox neither moves nor resizes
+    fn is_still(&self) -> bool {
+        self.value == Vec2::zero()
+            && self.resize == Vec2::zero()
+            && self.angular == 0.0
+            && self.orbit.is_none()
+    }
+}
+
+/// A circular path, used by `HbVel::orbit`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Orbit {
+    /// The center of the circle.
+    pub center: Vec2,
+
+    /// The angular velocity around `center` in radians per unit time,
+    /// counter-clockwise.
+    pub angular: f64,
+}
+
+impl Orbit {
+    fn advance(&self, pos: Vec2, time: f64) -> Vec2 {
+        self.center + (pos - self.center).rotate(self.angular * time)
+    }
+
+    // returns the speed of a hitbox at `pos` along the orbit
+    fn speed(&self, pos: Vec2) -> f64 {
+        self.angular.abs() * pos.dist(&self.center)
+    }
 }
 
 impl From<Vec2> for HbVel {
@@ -110,8 +183,8 @@ impl PlacedBounds for HbVel {
     fn bounds_center(&self) -> &Vec2 {
         &self.value
     }
-    fn bounds_dims(&self) -> &Vec2 {
-        &self.resize
+    fn bounds_dims(&self) -> Vec2 {
+        self.resize
     }
 }
 
@@ -140,7 +213,24 @@ impl Hitbox {
 
     fn advanced_shape(&self, time: f64) -> PlacedShape {
         assert!(time < HIGH_TIME, "requires time < {}", HIGH_TIME);
-        self.value.advance(self.vel.value, self.vel.resize, time)
+        let mut result = self
+            .value
+            .advance(self.vel.value, self.vel.resize, time)
+            .rotated(self.vel.angular * time);
+        if let Some(orbit) = self.vel.orbit {
+            result.pos = orbit.advance(self.value.pos, time);
+        }
+        result
+    }
+
+    // returns the hitbox with its width and height grown by twice `padding`
+    fn padded(&self, padding: f64) -> Hitbox {
+        let mut result = self.clone();
+        result.value.shape = self
+            .value
+            .shape
+            .resized(self.value.dims() + v2(padding, padding) * 2.0);
+        result
     }
 
     fn validate(&self, min_size: f64, present_time: f64) {
@@ -148,12 +238,38 @@ impl Hitbox {
             !self.vel.end_time.is_nan() && self.vel.end_time >= present_time,
             "end time must exceed present time"
         );
-        if self.value.kind() == ShapeKind::Circle {
+        if self.value.kind() == ShapeKind::Polygon {
             assert_eq!(
                 self.vel.resize.x, self.vel.resize.y,
-                "circle resize velocity must maintain aspect ratio"
+                "polygon resize velocity must grow it evenly"
             );
         }
+        assert!(
+            self.vel.angular.is_finite()
+                && (self.vel.angular == 0.0 || self.value.kind() == ShapeKind::OrientedRect),
+            "only an OrientedRect may have an angular velocity"
+        );
+        if let Some(orbit) = self.vel.orbit {
+            assert!(
+                orbit.angular.is_finite()
+                    && orbit.center.x.is_finite()
+                    && orbit.center.y.is_finite(),
+                "orbit must be finite"
+            );
+            assert!(
+                self.vel.value == Vec2::zero(),
+                "an orbiting hitbox may not have a linear velocity"
+            );
+        }
+        if self.value.kind() == ShapeKind::HalfPlane {
+            assert!(
+                self.vel.value == Vec2::zero()
+                    && self.vel.resize == Vec2::zero()
+                    && self.vel.orbit.is_none(),
+                "a half-plane may not move or resize"
+            );
+            return;
+        }
         assert!(
             self.value.dims().x >= min_size && self.value.dims().y >= min_size,
             "shape width/height must be at least {}",
@@ -162,6 +278,9 @@ impl Hitbox {
     }
 
     fn time_until_too_small(&self, min_size: f64) -> f64 {
+        if self.value.kind() == ShapeKind::HalfPlane {
+            return f64::INFINITY;
+        }
         let min_size = min_size * 0.9;
         assert!(self.value.dims().x > min_size && self.value.dims().y > min_size);
         let mut time = f64::INFINITY;
@@ -181,6 +300,8 @@ impl Hitbox {
             vel: DurHbVel {
                 value: self.vel.value,
                 resize: self.vel.resize,
+                angular: self.vel.angular,
+                orbit: self.vel.orbit,
                 duration: self.vel.end_time - time,
             },
         }
@@ -230,6 +351,82 @@ pub trait HbProfile: Copy {
         &DEFAULT_GROUPS
     }
 
+    /// Returns the sides of the hitbox that other hitboxes may collide with.
+    /// Default is `CardMask::full()`.
+    ///
+    /// A `Collide` event is only reported if the other hitbox touches this one
+    /// through one of these sides, in the sense of
+    /// `PlacedShape::masked_normal_from`. If the hitboxes begin overlapping
+    /// from any other direction, such as a character jumping up through a
+    /// one-way platform, then the overlap is ignored until the hitboxes
+    /// separate again. Only `Rect` hitboxes may use a mask other than full.
+    fn solid_sides(&self) -> CardMask {
+        CardMask::full()
+    }
+
+    /// Returns the margin of the proximity sensor around the hitbox, or `None`
+    /// if it is not a sensor. Default is `None`.
+    ///
+    /// While another hitbox that it can interact with is within `margin` of a
+    /// sensor, in the same sense as `padding` (so the width and height of the
+    /// sensor shape grow by twice the margin), the pair is in proximity.
+    /// `Enter` and `Exit` events are reported as pairs come into and go out of
+    /// proximity. These are independent of `Collide` and `Separate`, which are
+    /// still reported for the sensor as usual. If both hitboxes are sensors,
+    /// the larger margin is used. The margin must be positive.
+    fn sensor_margin(&self) -> Option<f64> {
+        None
+    }
+
+    /// Returns true if the hitbox reports when other hitboxes are entirely
+    /// inside of it. Default is false.
+    ///
+    /// A `Contain` event is reported when a hitbox that this one can interact
+    /// with becomes entirely inside of it, and an `Uncontain` event is
+    /// reported when that hitbox extends more than `padding` outside of it
+    /// again. These are independent of `Collide` and `Separate`.
+    fn is_container(&self) -> bool {
+        false
+    }
+
+    /// Returns the padding of the hitbox, or `None` to use the padding of the
+    /// `Collider`. Default is `None`.
+    ///
+    /// This takes the place of the padding of the `Collider` for this hitbox,
+    /// as described in `Collider::new`. A pair of hitboxes must be separated
+    /// by the larger padding of the two before they are considered separated,
+    /// and the width and height of the hitbox must be at least its padding.
+    /// A small padding suits fast projectiles, while a large padding stops
+    /// `Separate` events from flickering while a character rests on a floor.
+    /// The padding must be positive.
+    fn padding(&self) -> Option<f64> {
+        None
+    }
+
+    /// Returns how deeply this hitbox and `other` must overlap before a
+    /// `Collide` event is reported between them. Default is `0.0`.
+    ///
+    /// The depth is measured as the length of `PlacedShape::normal_from`, so
+    /// grazing touches between the pair are not reported. This method should
+    /// be commutative. A hitbox narrower than twice the depth can only be
+    /// overlapped as deeply as its own size allows, so for such a hitbox the
+    /// depth is effectively clamped.
+    fn collide_depth(&self, _other: &Self) -> f64 {
+        0.0
+    }
+
+    /// Returns the depth that this hitbox and `other` must overlap by in order
+    /// to stay collided. Default is `0.0`.
+    ///
+    /// A `Separate` event is reported once the depth of the overlap is more
+    /// than `padding` below this value, so a value between `0.0` and
+    /// `collide_depth` avoids a flurry of events while the depth hovers
+    /// around `collide_depth`. This method should be commutative and must not
+    /// exceed `collide_depth`.
+    fn separate_depth(&self, _other: &Self) -> f64 {
+        0.0
+    }
+
     /// Returns true if the pair of hitboxes should be checked for collisions.
     ///
     /// This method should be commutative. This method should be consistent with
@@ -0,0 +1,120 @@
+// Copyright 2016-2018 Matthew D. Michelotti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::HbSlot;
+use std::ops::{Index, IndexMut};
+
+// Slab is a dense store of values that are addressed by small integer slots.
+// Slots are reserved before their value is stored, and a value may be taken out
+// of its slot temporarily while it is being updated. Slots are reused once they
+// are freed.
+
+pub struct Slab<T> {
+    entries: Vec<Option<T>>,
+    free_slots: Vec<HbSlot>,
+}
+
+impl<T> Slab<T> {
+    pub fn new() -> Slab<T> {
+        Slab {
+            entries: Vec::new(),
+            free_slots: Vec::new(),
+        }
+    }
+
+    // returns a vacant slot that will not be handed out again until it is freed
+    pub fn reserve(&mut self) -> HbSlot {
+        self.free_slots.pop().unwrap_or_else(|| {
+            self.entries.push(None);
+            self.entries.len() - 1
+        })
+    }
+
+    pub fn put(&mut self, slot: HbSlot, value: T) {
+        let entry = &mut self.entries[slot];
+        assert!(entry.is_none(), "slot {} is occupied", slot);
+        *entry = Some(value);
+    }
+
+    pub fn take(&mut self, slot: HbSlot) -> T {
+        self.entries[slot]
+            .take()
+            .unwrap_or_else(|| panic!("slot {} is vacant", slot))
+    }
+
+    // frees a vacant slot so that it may be reserved again
+    pub fn free(&mut self, slot: HbSlot) {
+        assert!(self.entries[slot].is_none(), "slot {} is occupied", slot);
+        self.free_slots.push(slot);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (HbSlot, &T)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, entry)| entry.as_ref().map(|value| (slot, value)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (HbSlot, &mut T)> {
+        self.entries
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(slot, entry)| entry.as_mut().map(|value| (slot, value)))
+    }
+}
+
+impl<T> Index<HbSlot> for Slab<T> {
+    type Output = T;
+    fn index(&self, slot: HbSlot) -> &T {
+        self.entries[slot]
+            .as_ref()
+            .unwrap_or_else(|| panic!("slot {} is vacant", slot))
+    }
+}
+
+impl<T> IndexMut<HbSlot> for Slab<T> {
+    fn index_mut(&mut self, slot: HbSlot) -> &mut T {
+        self.entries[slot]
+            .as_mut()
+            .unwrap_or_else(|| panic!("slot {} is vacant", slot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slab() {
+        let mut slab = Slab::new();
+        let (a, b) = (slab.reserve(), slab.reserve());
+        slab.put(a, "a");
+        slab.put(b, "b");
+        assert_eq!(slab.iter().collect::<Vec<_>>(), vec![(a, &"a"), (b, &"b")]);
+        assert_eq!(slab.take(a), "a");
+        assert_eq!(slab.iter().collect::<Vec<_>>(), vec![(b, &"b")]);
+        slab[b] = "c";
+        slab.free(a);
+        assert_eq!(slab.reserve(), a);
+        assert_eq!(slab[b], "c");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_vacant_index() {
+        let mut slab: Slab<u32> = Slab::new();
+        let slot = slab.reserve();
+        let _ = slab[slot];
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright 2016-2018 Matthew D. Michelotti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::dur_hitbox::DurHitbox;
+use crate::core::{HbGroup, HbProfile};
+use crate::float::n64;
+use crate::geom::{self, v2, PlacedShape, Shape, Vec2};
+use std::f64;
+
+/// The `(column, row)` coordinates of a tile in a `TileLayer`.
+pub type TileCoord = (u32, u32);
+
+/// A static grid of solid or empty square tiles that hitboxes may collide
+/// with.
+///
+/// Tiles are not registered as individual hitboxes. Instead, a hitbox is
+/// either touching the solid tiles of the layer or it is not, so a hitbox
+/// that slides across the seam between two adjacent solid tiles will not
+/// generate any events.
+///
+/// Each solid tile fills its whole square. Tiles with other shapes, such as
+/// slopes or half-height ledges, are not supported by the layer and should be
+/// added to the `Collider` as ordinary hitboxes instead.
+#[derive(Clone, Debug)]
+pub struct TileLayer {
+    origin: Vec2,
+    tile_width: f64,
+    columns: u32,
+    rows: u32,
+    group: HbGroup,
+    solid: Vec<bool>,
+}
+
+impl TileLayer {
+    /// Constructs a new layer where all tiles are empty.
+    ///
+    /// `origin` is the position of the lowest corner of tile `(0, 0)`, and
+    /// column and row indices increase along the x and y axes respectively.
+    /// Hitboxes interact with the layer if `group` is among their
+    /// `interact_groups`.
+    pub fn new(
+        origin: Vec2,
+        tile_width: f64,
+        columns: u32,
+        rows: u32,
+        group: HbGroup,
+    ) -> TileLayer {
+        assert!(tile_width > 0.0, "requires tile_width > 0.0");
+        TileLayer {
+            origin,
+            tile_width,
+            columns,
+            rows,
+            group,
+            solid: vec![false; columns as usize * rows as usize],
+        }
+    }
+
+    /// Returns the position of the lowest corner of tile `(0, 0)`.
+    pub fn origin(&self) -> Vec2 {
+        self.origin
+    }
+
+    pub(crate) fn shift_origin(&mut self, delta: Vec2) {
+        self.origin -= delta;
+    }
+
+    /// Returns the width and height of each tile.
+    pub fn tile_width(&self) -> f64 {
+        self.tile_width
+    }
+
+    /// Returns the number of columns of tiles.
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// Returns the number of rows of tiles.
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Returns the group that hitboxes must interact with in order to collide
+    /// with the layer.
+    pub fn group(&self) -> HbGroup {
+        self.group
+    }
+
+    /// Returns true if the tile at `coord` is solid.
+    ///
+    /// Panics if `coord` is outside of the layer.
+    pub fn is_solid(&self, coord: TileCoord) -> bool {
+        self.solid[self.index(coord)]
+    }
+
+    /// Sets whether the tile at `coord` is solid.
+    ///
+    /// If the layer is already in use by a `Collider`, then
+    /// `Collider::set_tile_solid` should be used instead.
+    pub fn set_solid(&mut self, coord: TileCoord, solid: bool) {
+        let index = self.index(coord);
+        self.solid[index] = solid;
+    }
+
+    /// Returns the placed shape of the tile at `coord`.
+    pub fn tile_shape(&self, coord: TileCoord) -> PlacedShape {
+        let pos = self.origin + v2(coord.0 as f64 + 0.5, coord.1 as f64 + 0.5) * self.tile_width;
+        Shape::square(self.tile_width).place(pos)
+    }
+
+    /// Returns a small set of rectangles that cover the solid tiles of the
+    /// layer, as computed by `geom::merge_tiles`.
+    pub fn merged_rects(&self) -> Vec<PlacedShape> {
+        geom::merge_tiles(
+            self.origin,
+            self.tile_width,
+            self.columns as usize,
+            &self.solid,
+        )
+    }
+
+    fn index(&self, coord: TileCoord) -> usize {
+        assert!(
+            coord.0 < self.columns && coord.1 < self.rows,
+            "tile {:?} is outside of the layer",
+            coord
+        );
+        coord.1 as usize * self.columns as usize + coord.0 as usize
+    }
+
+    pub(crate) fn interacts_with<P: HbProfile>(&self, profile: &P) -> bool {
+        profile.group().is_some() && profile.interact_groups().contains(&self.group)
+    }
+
+    // returns the solid tiles that overlap or touch the given bounds
+    pub(crate) fn solid_tiles_in(&self, bounds: &PlacedShape) -> Vec<TileCoord> {
+        let (start_x, end_x) = self.index_range(bounds.min_x(), bounds.max_x(), 0);
+        let (start_y, end_y) = self.index_range(bounds.min_y(), bounds.max_y(), 1);
+        let mut result = Vec::new();
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                if self.is_solid((x, y)) {
+                    result.push((x, y));
+                }
+            }
+        }
+        result
+    }
+
+    fn index_range(&self, min: f64, max: f64, axis: usize) -> (u32, u32) {
+        let (origin, count) = if axis == 0 {
+            (self.origin.x, self.columns)
+        } else {
+            (self.origin.y, self.rows)
+        };
+        let start = ((min - origin) / self.tile_width).ceil() - 1.0;
+        let end = ((max - origin) / self.tile_width).floor() + 1.0;
+        let clamp = |val: f64| val.max(0.0).min(count as f64) as u32;
+        (clamp(start), clamp(end))
+    }
+
+    // returns the time until the hitbox first touches a solid tile, and that tile
+    pub(crate) fn collide_time(&self, hitbox: &DurHitbox) -> Option<(f64, TileCoord)> {
+        self.solid_tiles_in(&hitbox.bounding_box())
+            .into_iter()
+            .map(|coord| {
+                let tile = DurHitbox::new(self.tile_shape(coord));
+                (hitbox.collide_time(&tile), coord)
+            })
+            .filter(|&(time, _)| time < f64::INFINITY)
+            .min_by_key(|&(time, _)| n64(time))
+    }
+
+    // returns the time until the padded hitbox is no longer touching any solid
+    // tile, and the last tile that it touched
+    pub(crate) fn separate_time(
+        &self,
+        hitbox: &DurHitbox,
+        padding: f64,
+    ) -> (f64, Option<TileCoord>) {
+        let padded = hitbox.padded(padding);
+        let mut intervals: Vec<(f64, f64, TileCoord)> = self
+            .solid_tiles_in(&padded.bounding_box())
+            .into_iter()
+            .filter_map(|coord| {
+                let tile = DurHitbox::new(self.tile_shape(coord));
+                let start = if padded.value.overlaps(&tile.value) {
+                    0.0
+                } else {
+                    padded.collide_time(&tile)
+                };
+                if start == f64::INFINITY {
+                    None
+                } else {
+                    let end = start + hitbox.advanced(start).separate_time(&tile, padding);
+                    Some((start, end, coord))
+                }
+            })
+            .collect();
+        intervals.sort_by_key(|&(start, _, _)| n64(start));
+
+        // The hitbox stays in contact with the layer for as long as the overlap
+        // intervals of the individual tiles chain together.
+        let mut reach = 0.0;
+        let mut last_coord = None;
+        for (start, end, coord) in intervals {
+            if start > reach {
+                break;
+            }
+            if end >= reach {
+                reach = end;
+                last_coord = Some(coord);
+            }
+        }
+        (reach, last_coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solid_tiles_in() {
+        let mut tiles = TileLayer::new(v2(-2.0, 0.0), 1.0, 4, 3, 0);
+        for x in 0..4 {
+            tiles.set_solid((x, 0), true);
+        }
+        tiles.set_solid((2, 2), true);
+        let bounds = Shape::rect(v2(1.0, 2.0)).place(v2(0.5, 1.0));
+        assert_eq!(
+            tiles.solid_tiles_in(&bounds),
+            vec![(1, 0), (2, 0), (3, 0), (2, 2)]
+        );
+        let bounds = Shape::rect(v2(0.5, 0.5)).place(v2(-1.5, 1.5));
+        assert_eq!(tiles.solid_tiles_in(&bounds), vec![]);
+        let bounds = Shape::rect(v2(100.0, 100.0)).place(v2(0.0, 0.0));
+        assert_eq!(tiles.solid_tiles_in(&bounds).len(), 5);
+    }
+
+    #[test]
+    fn test_tile_shape() {
+        let tiles = TileLayer::new(v2(-2.0, 1.0), 2.0, 4, 3, 0);
+        assert_eq!(
+            tiles.tile_shape((1, 2)),
+            Shape::square(2.0).place(v2(1.0, 6.0))
+        );
+    }
+}
@@ -21,7 +21,7 @@ pub fn n64(val: f64) -> N64 {
     N64::new(val)
 }
 
-#[derive(PartialEq, PartialOrd, Copy, Clone, Default)]
+#[derive(PartialEq, Copy, Clone, Default)]
 pub struct N64 {
     val: f64,
 }
@@ -35,6 +35,12 @@ impl N64 {
 
 impl Eq for N64 {}
 
+impl PartialOrd for N64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Ord for N64 {
     fn cmp(&self, other: &Self) -> Ordering {
         self.val.partial_cmp(&other.val).unwrap()
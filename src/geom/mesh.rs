@@ -0,0 +1,181 @@
+// Copyright 2016-2018 Matthew D. Michelotti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::float::n64;
+use crate::geom::{v2, PlacedShape, Shape, ShapeKind, Vec2};
+
+// This module merges adjacent axis-aligned rectangles into larger ones using
+// greedy meshing.
+
+/// Merges the solid cells of a tile bitmap into a small set of rectangles
+/// covering the same area.
+///
+/// `solid` is laid out in rows of `columns` cells, with cell `(x, y)` at index
+/// `y * columns + x`. `origin` is the lowest corner of cell `(0, 0)`, and
+/// column and row indices increase along the x and y axes respectively.
+///
+/// Cells are merged greedily, first along the x axis and then along the y
+/// axis, so the result is not necessarily minimal, but is typically much
+/// smaller than the number of solid cells.
+// `is_multiple_of` would need Rust 1.87
+#[allow(clippy::manual_is_multiple_of)]
+pub fn merge_tiles(
+    origin: Vec2,
+    tile_width: f64,
+    columns: usize,
+    solid: &[bool],
+) -> Vec<PlacedShape> {
+    assert!(tile_width > 0.0, "requires tile_width > 0.0");
+    assert!(
+        columns > 0 && solid.len() % columns == 0,
+        "tile bitmap length must be a multiple of columns"
+    );
+    let coord = |index: usize| origin.x + index as f64 * tile_width;
+    let xs: Vec<f64> = (0..=columns).map(coord).collect();
+    let coord = |index: usize| origin.y + index as f64 * tile_width;
+    let ys: Vec<f64> = (0..=solid.len() / columns).map(coord).collect();
+    greedy_mesh(&xs, &ys, solid)
+}
+
+/// Merges a set of axis-aligned rectangles into a small set of rectangles
+/// covering the same area.
+///
+/// The rectangles may overlap each other. Edges are only considered shared if
+/// their coordinates are exactly equal. Panics if any shape is not a `Rect`.
+pub fn merge_rects(rects: &[PlacedShape]) -> Vec<PlacedShape> {
+    for rect in rects {
+        assert!(rect.kind() == ShapeKind::Rect, "can only merge rects");
+    }
+    let xs = sorted_coords(rects.iter().flat_map(|rect| [rect.min_x(), rect.max_x()]));
+    let ys = sorted_coords(rects.iter().flat_map(|rect| [rect.min_y(), rect.max_y()]));
+    if xs.len() < 2 || ys.len() < 2 {
+        return Vec::new();
+    }
+    let columns = xs.len() - 1;
+    let mut solid = vec![false; columns * (ys.len() - 1)];
+    for rect in rects {
+        let (start_x, end_x) = (index_of(&xs, rect.min_x()), index_of(&xs, rect.max_x()));
+        let (start_y, end_y) = (index_of(&ys, rect.min_y()), index_of(&ys, rect.max_y()));
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                solid[y * columns + x] = true;
+            }
+        }
+    }
+    greedy_mesh(&xs, &ys, &solid)
+}
+
+fn sorted_coords<I: Iterator<Item = f64>>(coords: I) -> Vec<f64> {
+    let mut result: Vec<f64> = coords.collect();
+    result.sort_by_key(|&coord| n64(coord));
+    result.dedup();
+    result
+}
+
+fn index_of(coords: &[f64], coord: f64) -> usize {
+    coords
+        .binary_search_by_key(&n64(coord), |&c| n64(c))
+        .unwrap()
+}
+
+// xs and ys are the boundaries of the cells, so there is one more of each than
+// there are columns and rows
+fn greedy_mesh(xs: &[f64], ys: &[f64], solid: &[bool]) -> Vec<PlacedShape> {
+    let columns = xs.len() - 1;
+    let rows = ys.len() - 1;
+    let mut used = vec![false; solid.len()];
+    let free = |used: &[bool], x: usize, y: usize| {
+        let index = y * columns + x;
+        solid[index] && !used[index]
+    };
+    let mut result = Vec::new();
+    for y in 0..rows {
+        for x in 0..columns {
+            if !free(&used, x, y) {
+                continue;
+            }
+            let mut end_x = x + 1;
+            while end_x < columns && free(&used, end_x, y) {
+                end_x += 1;
+            }
+            let mut end_y = y + 1;
+            while end_y < rows && (x..end_x).all(|x| free(&used, x, end_y)) {
+                end_y += 1;
+            }
+            for used_y in y..end_y {
+                for used_x in x..end_x {
+                    used[used_y * columns + used_x] = true;
+                }
+            }
+            let dims = v2(xs[end_x] - xs[x], ys[end_y] - ys[y]);
+            let pos = v2(xs[x] + dims.x * 0.5, ys[y] + dims.y * 0.5);
+            result.push(Shape::rect(dims).place(pos));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_tiles() {
+        #[rustfmt::skip]
+        let solid = [
+            true,  true,  true,  false,
+            true,  true,  false, false,
+            false, false, false, true,
+        ];
+        let rects = merge_tiles(v2(0.0, 10.0), 2.0, 4, &solid);
+        assert_eq!(
+            rects,
+            vec![
+                Shape::rect(v2(6.0, 2.0)).place(v2(3.0, 11.0)),
+                Shape::rect(v2(4.0, 2.0)).place(v2(2.0, 13.0)),
+                Shape::rect(v2(2.0, 2.0)).place(v2(7.0, 15.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_tiles_block() {
+        let rects = merge_tiles(v2(-1.0, -1.0), 0.5, 4, &[true; 12]);
+        assert_eq!(rects, vec![Shape::rect(v2(2.0, 1.5)).place(v2(0.0, -0.25))]);
+    }
+
+    #[test]
+    fn test_merge_rects() {
+        let rects = merge_rects(&[
+            Shape::square(1.0).place(v2(0.5, 0.5)),
+            Shape::square(1.0).place(v2(1.5, 0.5)),
+            Shape::rect(v2(2.0, 1.0)).place(v2(1.0, 1.5)),
+            Shape::square(1.0).place(v2(1.0, 0.5)),
+        ]);
+        assert_eq!(rects, vec![Shape::square(2.0).place(v2(1.0, 1.0))]);
+
+        let rects = merge_rects(&[
+            Shape::square(1.0).place(v2(0.5, 0.5)),
+            Shape::square(1.0).place(v2(2.5, 0.5)),
+        ]);
+        assert_eq!(rects.len(), 2);
+        assert!(merge_rects(&[]).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_merge_circles() {
+        merge_rects(&[Shape::circle(1.0).place(v2(0.0, 0.0))]);
+    }
+}
@@ -15,9 +15,11 @@
 //! Module containing geometry primitives.
 
 mod card;
+mod mesh;
 pub(crate) mod shape;
 mod vec;
 
 pub use self::card::*;
+pub use self::mesh::{merge_rects, merge_tiles};
 pub use self::shape::{PlacedShape, Shape, ShapeKind};
 pub use self::vec::*;
@@ -12,7 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use fnv::FnvHashMap;
+use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::f64::consts::PI;
 
 use crate::core::{HbVel, Hitbox};
 use crate::float::n64;
@@ -25,38 +28,66 @@ mod tests;
 /// Enumeration of kinds of shapes used by Collider.
 #[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
 pub enum ShapeKind {
-    /// Circle.  Requires width and height to match.
+    /// Circle, or an axis-aligned ellipse if its width and height differ.
     Circle,
     /// Axis-aligned rectangle.
     Rect,
+    /// Rectangle rotated counter-clockwise about its center by the shape's
+    /// `angle`.
+    OrientedRect,
+    /// Convex polygon, with vertices given relative to its position.
+    Polygon,
+    /// Unbounded half-plane, whose edge passes through its position and which
+    /// extends away from the direction of its normal.
+    HalfPlane,
 }
 
 /// Represents a shape, without any position.
 ///
 /// Each shape has a `width` and `height`, which are allowed to be negative.
+/// An `OrientedRect` also has an `angle`, which is zero for other kinds, and a
+/// `Polygon` also has a list of `vertices`, which is empty for other kinds. A
+/// `HalfPlane` has zero dims, and the `angle` of its normal.
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub struct Shape {
     kind: ShapeKind,
     dims: Vec2,
+    angle: f64,
+    // the cosine and sine of `angle`
+    axis: Vec2,
+    vertices: &'static [Vec2],
 }
 
 impl Shape {
     /// Constructs a new shape with the given `kind` and `dims` (width and
     /// height dimensions).
     ///
-    /// Dimensions must be non-negative. If `kind` is `Circle`, then the width
-    /// and height must match.
+    /// Dimensions must be non-negative. If `kind` is `Circle` and the width
+    /// and height differ, then the shape is an axis-aligned ellipse. `kind` may
+    /// not be `Polygon` or `HalfPlane`; use `Shape::polygon` or
+    /// `Shape::half_plane` instead.
     pub fn new(kind: ShapeKind, dims: Vec2) -> Shape {
         assert!(dims.x >= 0.0 && dims.y >= 0.0, "dims must be non-negative");
+        assert!(
+            kind != ShapeKind::Polygon,
+            "use Shape::polygon for polygons"
+        );
+        assert!(
+            kind != ShapeKind::HalfPlane,
+            "use Shape::half_plane for half-planes"
+        );
         Shape::with_any_dims(kind, dims)
     }
 
     // allows negative dims
     fn with_any_dims(kind: ShapeKind, dims: Vec2) -> Shape {
-        if kind == ShapeKind::Circle {
-            assert_eq!(dims.x, dims.y, "circle width must equal height");
+        Shape {
+            kind,
+            dims,
+            angle: 0.0,
+            axis: v2(1.0, 0.0),
+            vertices: &[],
         }
-        Shape { kind, dims }
     }
 
     /// Constructs a new circle shape, using `diam` as the width and height.
@@ -65,6 +96,13 @@ impl Shape {
         Shape::new(ShapeKind::Circle, v2(diam, diam))
     }
 
+    /// Constructs a new axis-aligned ellipse shape with the given `dims`
+    /// (width and height dimensions). Its kind is `Circle`.
+    #[inline]
+    pub fn ellipse(dims: Vec2) -> Shape {
+        Shape::new(ShapeKind::Circle, dims)
+    }
+
     /// Constructs a new axis-aligned rectangle shape with the given `dims`
     /// (width and height dimensions).
     #[inline]
@@ -78,6 +116,77 @@ impl Shape {
         Shape::new(ShapeKind::Rect, v2(width, width))
     }
 
+    /// Constructs a new rectangle shape with the given `dims` (width and height
+    /// dimensions), rotated counter-clockwise by `angle` radians.
+    pub fn oriented_rect(dims: Vec2, angle: f64) -> Shape {
+        assert!(angle.is_finite(), "angle must be finite");
+        Shape {
+            angle,
+            axis: v2(angle.cos(), angle.sin()),
+            ..Shape::new(ShapeKind::OrientedRect, dims)
+        }
+    }
+
+    /// Constructs a new convex polygon shape with the given `vertices`, which
+    /// are relative to the position of the shape and in counter-clockwise
+    /// order.
+    ///
+    /// The polygon must be strictly convex. The vertices are borrowed for the
+    /// `'static` lifetime so that `Shape` stays `Copy`. Polygons whose
+    /// vertices are only known at runtime may be constructed with
+    /// `Shape::interned_polygon` instead.
+    ///
+    /// The dims of the shape are the dims of the smallest box centered on its
+    /// position that holds all of the vertices, so the vertices should be
+    /// roughly centered around the origin.
+    pub fn polygon(vertices: &'static [Vec2]) -> Shape {
+        check_polygon(vertices);
+        Shape::polygon_unchecked(vertices)
+    }
+
+    /// Constructs a new convex polygon shape in the same way as
+    /// `Shape::polygon`, for `vertices` that are only known at runtime, such
+    /// as those loaded from a file.
+    ///
+    /// The vertices are copied into storage that lasts for the rest of the
+    /// program, so that the shape may borrow them and stay `Copy`. Equal lists
+    /// of vertices that are interned on the same thread share that storage,
+    /// so constructing the same polygons again, such as when reloading a
+    /// level, does not use any more memory.
+    pub fn interned_polygon(vertices: &[Vec2]) -> Shape {
+        check_polygon(vertices);
+        Shape::polygon_unchecked(intern_vertices(vertices))
+    }
+
+    fn polygon_unchecked(vertices: &'static [Vec2]) -> Shape {
+        Shape {
+            kind: ShapeKind::Polygon,
+            dims: polygon_dims(vertices),
+            angle: 0.0,
+            axis: v2(1.0, 0.0),
+            vertices,
+        }
+    }
+
+    /// Constructs a new half-plane shape, which covers everything on the
+    /// opposite side of its position from `normal`.
+    ///
+    /// For example, `Shape::half_plane(Card::PlusY.into()).place(v2(0.0, -10.0))`
+    /// is a floor covering everything below `y = -10.0`. A half-plane may not
+    /// move or resize, and it is not placed in the grid that Collider uses to
+    /// find nearby hitboxes, so it is tested against every hitbox that it may
+    /// interact with. Half-planes never interact with each other.
+    pub fn half_plane(normal: Vec2) -> Shape {
+        let normal = normal.normalize().expect("normal must be non-zero");
+        Shape {
+            kind: ShapeKind::HalfPlane,
+            dims: Vec2::zero(),
+            angle: normal.y.atan2(normal.x),
+            axis: normal,
+            vertices: &[],
+        }
+    }
+
     /// Returns the kind of shape.
     #[inline]
     pub fn kind(&self) -> ShapeKind {
@@ -90,6 +199,19 @@ impl Shape {
         self.dims
     }
 
+    /// Returns the counter-clockwise rotation of the shape in radians. For a
+    /// `HalfPlane`, this is the angle of its normal.
+    #[inline]
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    /// Returns the vertices of a `Polygon`, relative to its position.
+    #[inline]
+    pub fn vertices(&self) -> &'static [Vec2] {
+        self.vertices
+    }
+
     /// Shorthand for `PlacedShape::new(pos, self)`.
     #[inline]
     pub fn place(self, pos: Vec2) -> PlacedShape {
@@ -97,8 +219,165 @@ impl Shape {
     }
 
     pub(crate) fn advance(&self, resize_vel: Vec2, elapsed: f64) -> Shape {
-        Shape::with_any_dims(self.kind, self.dims + resize_vel * elapsed)
+        Shape {
+            angle: self.angle,
+            axis: self.axis,
+            vertices: self.vertices,
+            ..Shape::with_any_dims(self.kind, self.dims + resize_vel * elapsed)
+        }
+    }
+
+    // Returns the shape with new `dims`, keeping the rest of it. A polygon
+    // keeps its vertices, and is grown outward by half of the change in dims.
+    pub(crate) fn resized(&self, dims: Vec2) -> Shape {
+        assert!(dims.x >= 0.0 && dims.y >= 0.0, "dims must be non-negative");
+        Shape { dims, ..*self }
+    }
+
+    // returns the distance from the center to the farthest point of the shape
+    pub(crate) fn circumradius(&self) -> f64 {
+        match self.kind {
+            ShapeKind::Circle => self.dims.x.max(self.dims.y) * 0.5,
+            ShapeKind::Rect | ShapeKind::OrientedRect => self.dims.len() * 0.5,
+            ShapeKind::Polygon => {
+                self.vertices
+                    .iter()
+                    .map(|vertex| vertex.len())
+                    .fold(0.0, f64::max)
+                    + self.polygon_padding()
+            }
+            // this only bounds how fast points move as the shape turns, and a
+            // half-plane never turns
+            ShapeKind::HalfPlane => 0.0,
+        }
+    }
+
+    // returns the unit vector at the angle of the shape, which is the normal of
+    // a half-plane
+    pub(crate) fn axis(&self) -> Vec2 {
+        self.axis
+    }
+
+    // returns true if the shape is a `Circle` with a different width and height
+    pub(crate) fn is_ellipse(&self) -> bool {
+        self.kind == ShapeKind::Circle && self.dims.x != self.dims.y
+    }
+
+    // rotates `vec` from the frame of the shape to the world frame
+    pub(crate) fn world_vec(&self, vec: Vec2) -> Vec2 {
+        let (cos, sin) = (self.axis.x, self.axis.y);
+        v2(cos * vec.x - sin * vec.y, sin * vec.x + cos * vec.y)
+    }
+
+    // rotates `vec` from the world frame to the frame of the shape
+    pub(crate) fn local_vec(&self, vec: Vec2) -> Vec2 {
+        let (cos, sin) = (self.axis.x, self.axis.y);
+        v2(cos * vec.x + sin * vec.y, cos * vec.y - sin * vec.x)
+    }
+
+    // returns how far a polygon has been grown outward from its vertices
+    pub(crate) fn polygon_padding(&self) -> f64 {
+        if self.kind == ShapeKind::Polygon {
+            (self.dims.x - polygon_dims(self.vertices).x) * 0.5
+        } else {
+            0.0
+        }
+    }
+}
+
+// Returns the unit vector that minimizes `f`, along with that minimum, where
+// `f` is continuous, such as a sum of support functions. The minimum is found
+// by sampling directions and refining the best sample, so a narrower minimum
+// elsewhere may be missed.
+pub(crate) fn min_over_dirs<F>(f: F) -> (Vec2, f64)
+where
+    F: Fn(Vec2) -> f64,
+{
+    let dir = |angle: f64| v2(angle.cos(), angle.sin());
+    let step = 2.0 * PI / f64::from(DIR_SAMPLES);
+    let (best, best_value) = (0..DIR_SAMPLES)
+        .map(|index| {
+            let angle = f64::from(index) * step;
+            (angle, f(dir(angle)))
+        })
+        .min_by_key(|&(_, value)| n64(value))
+        .unwrap();
+
+    // golden-section search between the neighbouring samples
+    let ratio = (5.0f64.sqrt() - 1.0) * 0.5;
+    let (mut low, mut high) = (best - step, best + step);
+    let (mut mid_low, mut mid_high) = (high - ratio * (high - low), low + ratio * (high - low));
+    let (mut low_value, mut high_value) = (f(dir(mid_low)), f(dir(mid_high)));
+    for _ in 0..DIR_REFINE_STEPS {
+        if low_value < high_value {
+            high = mid_high;
+            mid_high = mid_low;
+            high_value = low_value;
+            mid_low = high - ratio * (high - low);
+            low_value = f(dir(mid_low));
+        } else {
+            low = mid_low;
+            mid_low = mid_high;
+            low_value = high_value;
+            mid_high = low + ratio * (high - low);
+            high_value = f(dir(mid_high));
+        }
     }
+    let refined = dir((low + high) * 0.5);
+    let refined_value = f(refined);
+    if refined_value < best_value {
+        (refined, refined_value)
+    } else {
+        (dir(best), best_value)
+    }
+}
+
+const DIR_SAMPLES: u32 = 64;
+const DIR_REFINE_STEPS: u32 = 60;
+
+// panics unless `vertices` form a strictly convex polygon in counter-clockwise
+// order
+fn check_polygon(vertices: &[Vec2]) {
+    assert!(vertices.len() >= 3, "polygon needs at least 3 vertices");
+    for (index, &vertex) in vertices.iter().enumerate() {
+        let next = vertices[(index + 1) % vertices.len()];
+        let after = vertices[(index + 2) % vertices.len()];
+        let (edge, next_edge) = (next - vertex, after - next);
+        let turn = edge.x * next_edge.y - edge.y * next_edge.x;
+        assert!(
+            turn > 0.0,
+            "polygon must be strictly convex with vertices in counter-clockwise order"
+        );
+    }
+}
+
+// returns a copy of `vertices` that lasts for the rest of the program, reusing
+// the copy of any equal list of vertices that was interned on this thread
+fn intern_vertices(vertices: &[Vec2]) -> &'static [Vec2] {
+    thread_local! {
+        static INTERNED: RefCell<FnvHashMap<Vec<u64>, &'static [Vec2]>> =
+            RefCell::new(FnvHashMap::default());
+    }
+    let mut key = Vec::with_capacity(vertices.len() * 2);
+    for vertex in vertices {
+        key.push(vertex.x.to_bits());
+        key.push(vertex.y.to_bits());
+    }
+    INTERNED.with(|interned| {
+        *interned
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(|| Box::leak(vertices.to_vec().into_boxed_slice()))
+    })
+}
+
+// returns the dims of the smallest box centered on the origin that holds all
+// of the `vertices`
+fn polygon_dims(vertices: &[Vec2]) -> Vec2 {
+    let half = vertices.iter().fold(Vec2::zero(), |half, vertex| {
+        v2(half.x.max(vertex.x.abs()), half.y.max(vertex.y.abs()))
+    });
+    half * 2.0
 }
 
 /// Represents a shape with a position.
@@ -166,13 +445,25 @@ impl PlacedShape {
     /// `other`.
     ///
     /// (As a minor caveat, when computing the normal between two `Rect` shapes,
-    /// the direction will always be axis-aligned.)
+    /// the direction will always be axis-aligned. The normal involving an
+    /// ellipse is found numerically.)
     pub fn normal_from(&self, other: &PlacedShape) -> DirVec2 {
         match (self.kind(), other.kind()) {
+            (ShapeKind::HalfPlane, ShapeKind::HalfPlane) => {
+                panic!("cannot find the normal between two half-planes")
+            }
+            (ShapeKind::HalfPlane, _) => normals::half_plane_normal(self, other),
+            (_, ShapeKind::HalfPlane) => normals::half_plane_normal(other, self).flip(),
+            _ if self.shape.is_ellipse() || other.shape.is_ellipse() => {
+                normals::ellipse_normal(self, other)
+            }
             (ShapeKind::Rect, ShapeKind::Rect) => normals::rect_rect_normal(self, other),
             (ShapeKind::Rect, ShapeKind::Circle) => normals::rect_circle_normal(self, other),
             (ShapeKind::Circle, ShapeKind::Rect) => normals::rect_circle_normal(other, self).flip(),
             (ShapeKind::Circle, ShapeKind::Circle) => normals::circle_circle_normal(self, other),
+            (_, ShapeKind::Circle) => normals::polygon_circle_normal(self, other),
+            (ShapeKind::Circle, _) => normals::polygon_circle_normal(other, self).flip(),
+            _ => normals::polygon_normal(self, other),
         }
     }
 
@@ -183,8 +474,39 @@ impl PlacedShape {
     /// `mask` will not be returned, and the next-in-line normal vector will be
     /// used instead. This function panics if `mask` is empty, or if both shapes
     /// are circles and `mask` is anything but full.
+    ///
+    /// An `OrientedRect`, `Polygon` or ellipse is treated as its bounding box,
+    /// unless `mask` is full. Since a half-plane can only be escaped away from
+    /// its normal, the normal involving a `HalfPlane` is the permitted
+    /// direction that escapes it the soonest, and this panics if there is none.
     pub fn masked_normal_from(&self, other: &PlacedShape, mask: CardMask) -> DirVec2 {
         match (self.kind(), other.kind()) {
+            (ShapeKind::HalfPlane, _) | (_, ShapeKind::HalfPlane) if mask == CardMask::full() => {
+                self.normal_from(other)
+            }
+            (ShapeKind::HalfPlane, _) => normals::masked_half_plane_normal(self, other, mask),
+            (_, ShapeKind::HalfPlane) => {
+                normals::masked_half_plane_normal(other, self, mask.flip()).flip()
+            }
+            _ if self.shape.is_ellipse() || other.shape.is_ellipse() => {
+                if mask == CardMask::full() {
+                    self.normal_from(other)
+                } else {
+                    self.axis_aligned()
+                        .masked_normal_from(&other.axis_aligned(), mask)
+                }
+            }
+            (ShapeKind::OrientedRect, _)
+            | (_, ShapeKind::OrientedRect)
+            | (ShapeKind::Polygon, _)
+            | (_, ShapeKind::Polygon) => {
+                if mask == CardMask::full() {
+                    self.normal_from(other)
+                } else {
+                    self.axis_aligned()
+                        .masked_normal_from(&other.axis_aligned(), mask)
+                }
+            }
             (ShapeKind::Rect, ShapeKind::Rect) => {
                 normals::masked_rect_rect_normal(self, other, mask)
             }
@@ -206,9 +528,15 @@ impl PlacedShape {
     /// shapes.
     pub fn contact_point(&self, other: &PlacedShape) -> Vec2 {
         match (self.kind(), other.kind()) {
+            (ShapeKind::HalfPlane, ShapeKind::HalfPlane) => {
+                panic!("cannot find the contact between two half-planes")
+            }
+            (ShapeKind::HalfPlane, _) => normals::half_plane_contact(self, other),
+            (_, ShapeKind::HalfPlane) => normals::half_plane_contact(other, self),
             (ShapeKind::Rect, ShapeKind::Rect) => normals::rect_rect_contact(self, other),
             (ShapeKind::Circle, _) => normals::circle_any_contact(self, other),
-            (ShapeKind::Rect, ShapeKind::Circle) => normals::circle_any_contact(other, self),
+            (_, ShapeKind::Circle) => normals::circle_any_contact(other, self),
+            _ => normals::polygon_contact(self, other),
         }
     }
 
@@ -224,6 +552,12 @@ impl PlacedShape {
         Hitbox::new(self, HbVel::moving_until(vel, end_time))
     }
 
+    /// Shorthand for `Hitbox::new(self, HbVel::orbiting(center, angular))`.
+    #[inline]
+    pub fn orbiting(self, center: Vec2, angular: f64) -> Hitbox {
+        Hitbox::new(self, HbVel::orbiting(center, angular))
+    }
+
     /// Shorthand for `Hitbox::new(self, HbVel::still())`.
     #[inline]
     pub fn still(self) -> Hitbox {
@@ -243,7 +577,88 @@ impl PlacedShape {
     }
 
     pub(crate) fn as_rect(&self) -> PlacedShape {
-        PlacedShape::new(self.pos, Shape::rect(self.shape.dims()))
+        PlacedShape::new(self.pos, Shape::rect(self.bounds_dims()))
+    }
+
+    // returns the shape with an `OrientedRect`, `Polygon` or ellipse replaced
+    // by its bounding box
+    fn axis_aligned(&self) -> PlacedShape {
+        match self.kind() {
+            ShapeKind::OrientedRect | ShapeKind::Polygon => self.as_rect(),
+            ShapeKind::Circle if self.shape.is_ellipse() => self.as_rect(),
+            ShapeKind::Rect | ShapeKind::Circle | ShapeKind::HalfPlane => *self,
+        }
+    }
+
+    // Returns the corners of a rect or polygon in counter-clockwise order. A
+    // padded polygon extends `polygon_padding` beyond its corners.
+    pub(crate) fn corners(&self) -> Vec<Vec2> {
+        match self.kind() {
+            ShapeKind::Circle | ShapeKind::HalfPlane => panic!("expected a rect or polygon"),
+            ShapeKind::Polygon => self
+                .shape
+                .vertices
+                .iter()
+                .map(|&vertex| self.pos + vertex)
+                .collect(),
+            ShapeKind::Rect | ShapeKind::OrientedRect => {
+                let half = self.dims() * 0.5;
+                [
+                    v2(half.x, half.y),
+                    v2(-half.x, half.y),
+                    v2(-half.x, -half.y),
+                    v2(half.x, -half.y),
+                ]
+                .iter()
+                .map(|&corner| self.pos + self.shape.world_vec(corner))
+                .collect()
+            }
+        }
+    }
+
+    // Returns the outward unit normals of the edges of a rect or polygon, where
+    // the edge from corner `i` to corner `i + 1` has normal `i`.
+    pub(crate) fn edge_normals(&self) -> Vec<Vec2> {
+        match self.kind() {
+            ShapeKind::Circle => return Vec::new(),
+            ShapeKind::HalfPlane => return vec![self.shape.axis],
+            _ => (),
+        }
+        let corners = self.corners();
+        (0..corners.len())
+            .map(|index| {
+                let edge = corners[(index + 1) % corners.len()] - corners[index];
+                v2(edge.y, -edge.x).normalize().unwrap()
+            })
+            .collect()
+    }
+
+    // returns how far the shape extends along the unit vector `dir`
+    pub(crate) fn support(&self, dir: Vec2) -> f64 {
+        let extent = match self.kind() {
+            ShapeKind::Circle => v2(self.dims().x * dir.x, self.dims().y * dir.y).len() * 0.5,
+            ShapeKind::Rect | ShapeKind::OrientedRect => {
+                let local = self.shape.local_vec(dir);
+                (self.dims().x * local.x.abs() + self.dims().y * local.y.abs()) * 0.5
+            }
+            ShapeKind::Polygon => {
+                self.shape
+                    .vertices
+                    .iter()
+                    .map(|&vertex| vertex * dir)
+                    .fold(f64::NEG_INFINITY, f64::max)
+                    + self.shape.polygon_padding()
+            }
+            // a half-plane is unbounded in every direction but its normal
+            ShapeKind::HalfPlane => {
+                if dir * self.shape.axis >= 1.0 - 1e-9 {
+                    self.dims().x * 0.5
+                } else {
+                    return f64::INFINITY;
+                }
+            }
+        };
+        self.pos * dir + extent
     }
 
     pub(crate) fn bounding_box(&self, other: &PlacedShape) -> PlacedShape {
@@ -263,11 +678,25 @@ impl PlacedShape {
             self.shape.advance(resize_vel, elapsed),
         )
     }
+
+    pub(crate) fn rotated(&self, angle: f64) -> PlacedShape {
+        if angle == 0.0 {
+            return *self;
+        }
+        assert!(
+            self.kind() == ShapeKind::OrientedRect,
+            "only an OrientedRect may rotate"
+        );
+        let mut result = *self;
+        result.shape.angle += angle;
+        result.shape.axis = v2(result.shape.angle.cos(), result.shape.angle.sin());
+        result
+    }
 }
 
 pub(crate) trait PlacedBounds {
     fn bounds_center(&self) -> &Vec2;
-    fn bounds_dims(&self) -> &Vec2;
+    fn bounds_dims(&self) -> Vec2;
 
     fn bounds_bottom(&self) -> f64 {
         self.bounds_center().y - self.bounds_dims().y * 0.5
@@ -322,8 +751,15 @@ impl PlacedBounds for PlacedShape {
     fn bounds_center(&self) -> &Vec2 {
         &self.pos
     }
-    fn bounds_dims(&self) -> &Vec2 {
-        &self.shape.dims
+    fn bounds_dims(&self) -> Vec2 {
+        let dims = self.shape.dims;
+        match self.kind() {
+            ShapeKind::HalfPlane => return v2(f64::INFINITY, f64::INFINITY),
+            ShapeKind::Rect | ShapeKind::Circle => return dims,
+            ShapeKind::OrientedRect | ShapeKind::Polygon => (),
+        }
+        let (cos, sin) = (self.shape.axis.x.abs(), self.shape.axis.y.abs());
+        v2(cos * dims.x + sin * dims.y, sin * dims.x + cos * dims.y)
     }
 }
 
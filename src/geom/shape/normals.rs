@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use crate::float::n64;
-use crate::geom::shape::{PlacedBounds, Sector};
+use crate::geom::shape::{min_over_dirs, PlacedBounds, Sector};
 use crate::geom::*;
 
 // This module contains methods to solve for the normal vector
@@ -85,6 +85,96 @@ pub fn masked_rect_circle_normal(dst: &PlacedShape, src: &PlacedShape, mask: Car
     }
 }
 
+// Normal between a rect or polygon and a circle. A circle whose center is
+// inside of `dst` is pushed out through the nearest edge, and otherwise the
+// normal points from the nearest point on `dst` to the center.
+pub fn polygon_circle_normal(dst: &PlacedShape, src: &PlacedShape) -> DirVec2 {
+    let reach = src.dims().x * 0.5 + dst.shape.polygon_padding();
+    let corners = dst.corners();
+    let (normal, depth) = dst
+        .edge_normals()
+        .into_iter()
+        .zip(corners.iter())
+        .map(|(normal, &corner)| (normal, (src.pos - corner) * normal))
+        .max_by_key(|&(_, dist)| n64(dist))
+        .unwrap();
+    if depth <= 0.0 {
+        return DirVec2::new(-normal, reach - depth);
+    }
+    let nearest = edges(&corners)
+        .map(|edge| nearest_on_edge(src.pos, edge))
+        .min_by_key(|point| n64(point.dist_sq(&src.pos)))
+        .unwrap();
+    let dist = nearest.dist(&src.pos);
+    if dist > 0.0 {
+        DirVec2::new(nearest - src.pos, reach - dist)
+    } else {
+        DirVec2::new(-normal, reach)
+    }
+}
+
+// Normal between two shapes, at least one of which is an ellipse. For convex
+// shapes, the least overlap along any axis is the overlap of the shapes if
+// they are overlapping, and the negated distance between them otherwise.
+pub fn ellipse_normal(dst: &PlacedShape, src: &PlacedShape) -> DirVec2 {
+    let (axis, overlap) = min_over_dirs(|axis| src.support(axis) + dst.support(-axis));
+    DirVec2::new(axis, overlap)
+}
+
+// Normal between two rects or polygons that are not both axis-aligned rects.
+// Overlapping shapes use the separating axis with the least overlap, and
+// disjoint shapes use the direction between their nearest points.
+pub fn polygon_normal(dst: &PlacedShape, src: &PlacedShape) -> DirVec2 {
+    let (axis, overlap) = dst
+        .edge_normals()
+        .into_iter()
+        .chain(src.edge_normals())
+        .flat_map(|axis| vec![axis, -axis])
+        .map(|axis| (axis, src.support(axis) + dst.support(-axis)))
+        .min_by_key(|&(_, overlap)| n64(overlap))
+        .unwrap();
+    if overlap >= 0.0 {
+        return DirVec2::new(axis, overlap);
+    }
+    let (dst_point, src_point) = nearest_points(&dst.corners(), &src.corners());
+    let padding = dst.shape.polygon_padding() + src.shape.polygon_padding();
+    let dist = dst_point.dist(&src_point);
+    if dist - padding > -overlap {
+        DirVec2::new(dst_point - src_point, padding - dist)
+    } else {
+        DirVec2::new(axis, overlap)
+    }
+}
+
+// returns the nearest pair of points between the boundaries of two disjoint
+// polygons, as (point on `a`, point on `b`)
+fn nearest_points(a: &[Vec2], b: &[Vec2]) -> (Vec2, Vec2) {
+    let a_to_b = a
+        .iter()
+        .flat_map(|&vertex| edges(b).map(move |edge| (vertex, nearest_on_edge(vertex, edge))));
+    let b_to_a = b
+        .iter()
+        .flat_map(|&vertex| edges(a).map(move |edge| (nearest_on_edge(vertex, edge), vertex)));
+    a_to_b
+        .chain(b_to_a)
+        .min_by_key(|&(a_point, b_point)| n64(a_point.dist_sq(&b_point)))
+        .unwrap()
+}
+
+fn edges(vertices: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    (0..vertices.len()).map(move |i| (vertices[i], vertices[(i + 1) % vertices.len()]))
+}
+
+fn nearest_on_edge(point: Vec2, (start, end): (Vec2, Vec2)) -> Vec2 {
+    let edge = end - start;
+    let len_sq = edge.len_sq();
+    if len_sq == 0.0 {
+        start
+    } else {
+        start.lerp(end, ((point - start) * edge / len_sq).clamp(0.0, 1.0))
+    }
+}
+
 fn mask_has_corner_sector(sector: Sector, mask: CardMask) -> bool {
     if let Some((h_card, v_card)) = sector.corner_cards() {
         mask[h_card] && mask[v_card]
@@ -95,7 +185,65 @@ fn mask_has_corner_sector(sector: Sector, mask: CardMask) -> bool {
 
 pub fn circle_any_contact(a: &PlacedShape, b: &PlacedShape) -> Vec2 {
     let normal = a.normal_from(b);
-    a.pos + normal.dir() * (normal.len() - a.shape.dims().x) * 0.5
+    if !a.shape.is_ellipse() {
+        return a.pos + normal.dir() * (normal.len() - a.shape.dims().x) * 0.5;
+    }
+    support_point(a, -normal.dir()) + normal.dir() * normal.len() * 0.5
+}
+
+// Normal from a shape to a half-plane, which always points into the
+// half-plane. The overlap is how far the shape reaches past its edge.
+pub fn half_plane_normal(dst: &PlacedShape, src: &PlacedShape) -> DirVec2 {
+    let normal = dst.shape.axis;
+    let level = dst.pos * normal + dst.dims().x * 0.5;
+    DirVec2::new(-normal, level + src.support(-normal))
+}
+
+// Uses the permitted cardinal direction that is closest to the normal, which
+// must lead out of the half-plane.
+pub fn masked_half_plane_normal(dst: &PlacedShape, src: &PlacedShape, mask: CardMask) -> DirVec2 {
+    let normal = half_plane_normal(dst, src);
+    let (card, alignment) = Card::values()
+        .iter()
+        .cloned()
+        .filter(|&card| mask[card])
+        .map(|card| (card, Vec2::from(card) * normal.dir()))
+        .max_by_key(|&(_, alignment)| n64(alignment))
+        .unwrap_or_else(|| panic!("CardMask must be non-empty"));
+    assert!(
+        alignment > 0.0,
+        "CardMask must permit a direction out of the half-plane"
+    );
+    DirVec2::new(card.into(), normal.len() / alignment)
+}
+
+// takes the middle of the part of `other` that reaches past the edge of the
+// half-plane
+pub fn half_plane_contact(half_plane: &PlacedShape, other: &PlacedShape) -> Vec2 {
+    let normal = half_plane_normal(half_plane, other);
+    support_point(other, normal.dir()) - normal.dir() * normal.len() * 0.5
+}
+
+// Returns the point of a shape that extends farthest along the unit vector
+// `dir`, taking the middle of an edge of a rect or polygon that faces `dir`.
+fn support_point(shape: &PlacedShape, dir: Vec2) -> Vec2 {
+    match shape.kind() {
+        ShapeKind::Circle => {
+            let half = shape.dims() * 0.5;
+            let extent = v2(half.x * dir.x, half.y * dir.y).len();
+            if extent > 0.0 {
+                shape.pos + v2(half.x * half.x * dir.x, half.y * half.y * dir.y) * (1.0 / extent)
+            } else {
+                shape.pos
+            }
+        }
+        ShapeKind::HalfPlane => panic!("a half-plane has no support point"),
+        ShapeKind::Rect | ShapeKind::OrientedRect | ShapeKind::Polygon => {
+            let tangent = v2(-dir.y, dir.x);
+            let (min, max) = facing_span(&shape.corners(), dir, tangent);
+            dir * shape.support(dir) + tangent * ((min + max) * 0.5)
+        }
+    }
 }
 
 pub fn rect_rect_contact(a: &PlacedShape, b: &PlacedShape) -> Vec2 {
@@ -108,3 +256,40 @@ pub fn rect_rect_contact(a: &PlacedShape, b: &PlacedShape) -> Vec2 {
 fn rect_rect_contact_1d(a_min: f64, a_max: f64, b_min: f64, b_max: f64) -> f64 {
     0.5 * (a_min.max(b_min) + b_max.min(a_max))
 }
+
+// Finds the features of each shape that face the other along the normal, and
+// takes the middle of where they overlap. Agrees with `rect_rect_contact` for
+// axis-aligned rects.
+pub fn polygon_contact(a: &PlacedShape, b: &PlacedShape) -> Vec2 {
+    let dir = a.normal_from(b).dir();
+    let tangent = v2(-dir.y, dir.x);
+    let a_level = -a.support(-dir);
+    let b_level = b.support(dir);
+    let (a_min, a_max) = facing_span(&a.corners(), -dir, tangent);
+    let (b_min, b_max) = facing_span(&b.corners(), dir, tangent);
+    dir * (0.5 * (a_level + b_level)) + tangent * (0.5 * (a_min.max(b_min) + a_max.min(b_max)))
+}
+
+// Returns the extent along `tangent` of the feature of a counter-clockwise
+// polygon that faces `dir`. This is an edge if one nearly faces `dir`, which
+// absorbs rounding error in `dir`, and a vertex otherwise.
+fn facing_span(vertices: &[Vec2], dir: Vec2, tangent: Vec2) -> (f64, f64) {
+    let (alignment, (start, end)) = edges(vertices)
+        .map(|(start, end)| {
+            let outward = v2(end.y - start.y, start.x - end.x);
+            let alignment = outward.normalize().map_or(-1.0, |outward| outward * dir);
+            (alignment, (start, end))
+        })
+        .max_by_key(|&(alignment, _)| n64(alignment))
+        .unwrap();
+    if alignment >= 1.0 - 1e-9 {
+        let (start, end) = (start * tangent, end * tangent);
+        (start.min(end), start.max(end))
+    } else {
+        let vertex = *vertices
+            .iter()
+            .max_by_key(|&&vertex| n64(vertex * dir))
+            .unwrap();
+        (vertex * tangent, vertex * tangent)
+    }
+}
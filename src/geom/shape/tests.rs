@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use crate::geom::*;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
 
 #[test]
 fn test_circle_advance() {
@@ -33,10 +34,12 @@ fn test_rect_advance() {
 }
 
 #[test]
-#[should_panic]
-fn test_illegal_circle_advance() {
+fn test_circle_advance_to_ellipse() {
     let shape = Shape::circle(2.0).place(v2(3.0, 5.0));
-    shape.advance(v2(1.0, 2.0), v2(-0.25, -0.24), 2.0);
+    assert_eq!(
+        shape.advance(v2(1.0, 2.0), v2(-0.25, 0.5), 2.0),
+        Shape::ellipse(v2(1.5, 3.0)).place(v2(5.0, 9.0))
+    );
 }
 
 #[test]
@@ -170,3 +173,255 @@ fn test_circle_rect_contact() {
     assert_eq!(a.contact_point(&b), v2(4.0, 15.0));
     assert_eq!(b.contact_point(&a), v2(4.0, 15.0));
 }
+
+#[test]
+fn test_oriented_rect_bounds() {
+    let shape = Shape::oriented_rect(v2(2.0, 4.0), FRAC_PI_2).place(v2(1.0, 1.0));
+    assert!((shape.min_x() + 1.0).abs() < 1e-9);
+    assert!((shape.max_y() - 2.0).abs() < 1e-9);
+    let diamond = Shape::oriented_rect(v2(2.0, 2.0), FRAC_PI_4).place(v2(0.0, 0.0));
+    assert!((diamond.max_x() - (2.0f64).sqrt()).abs() < 1e-9);
+    assert!((diamond.min_y() + (2.0f64).sqrt()).abs() < 1e-9);
+}
+
+#[test]
+fn test_oriented_rect_rect_normal() {
+    let sqrt2 = (2.0f64).sqrt();
+    let dst = Shape::oriented_rect(v2(2.0, 2.0), FRAC_PI_4).place(v2(0.0, 0.0));
+    let src = Shape::square(2.0).place(v2(2.0, 0.5));
+    let normal = dst.normal_from(&src);
+    assert!((normal.dir() - v2(-1.0, 0.0)).len() < 1e-9);
+    assert!((normal.len() - (sqrt2 - 1.0)).abs() < 1e-9);
+    assert!((src.normal_from(&dst).len() - normal.len()).abs() < 1e-9);
+
+    // apart, the normal points between the nearest points
+    let src = Shape::square(2.0).place(v2(3.0, 2.0));
+    let normal = dst.normal_from(&src);
+    assert!((normal.dir() - v2(-1.0, -1.0).normalize().unwrap()).len() < 1e-9);
+    assert!((normal.len() + (3.0 - sqrt2) / sqrt2).abs() < 1e-9);
+}
+
+#[test]
+fn test_oriented_rect_circle_normal() {
+    let sqrt2 = (2.0f64).sqrt();
+    let dst = Shape::oriented_rect(v2(2.0, 2.0), FRAC_PI_4).place(v2(0.0, 0.0));
+    let src = Shape::circle(1.0).place(v2(1.5, 0.0));
+    let normal = dst.normal_from(&src);
+    assert!((normal.dir() - v2(-1.0, 0.0)).len() < 1e-9);
+    assert!((normal.len() - (sqrt2 - 1.0)).abs() < 1e-9);
+
+    // facing an edge, the normal is perpendicular to it
+    let src = Shape::circle(1.0).place(v2(1.0, 1.0));
+    let normal = src.normal_from(&dst);
+    assert!((normal.dir() - v2(1.0, 1.0).normalize().unwrap()).len() < 1e-9);
+    assert!((normal.len() - (1.5 - sqrt2)).abs() < 1e-9);
+}
+
+#[test]
+fn test_masked_oriented_rect_normal() {
+    let dst = Shape::oriented_rect(v2(2.0, 2.0), FRAC_PI_4).place(v2(0.0, 0.0));
+    let src = Shape::square(2.0).place(v2(2.0, 0.5));
+    let mut mask = CardMask::full();
+    mask[Card::MinusX] = false;
+    let normal = dst.masked_normal_from(&src, mask);
+    assert!((normal.dir() - v2(0.0, -1.0)).len() < 1e-9);
+    assert!((normal.len() - (0.5 + (2.0f64).sqrt())).abs() < 1e-9);
+}
+
+#[test]
+fn test_oriented_rect_contact() {
+    let sqrt2 = (2.0f64).sqrt();
+    let a = Shape::oriented_rect(v2(2.0, 2.0), FRAC_PI_4).place(v2(0.0, 0.0));
+    let b = Shape::square(2.0).place(v2(2.0, 0.0));
+    assert!((a.contact_point(&b) - v2((1.0 + sqrt2) * 0.5, 0.0)).len() < 1e-9);
+    assert!((b.contact_point(&a) - v2((1.0 + sqrt2) * 0.5, 0.0)).len() < 1e-9);
+
+    // unrotated, this agrees with the axis-aligned contact point
+    let a = Shape::oriented_rect(v2(4.0, 2.0), 0.0).place(v2(4.0, 10.0));
+    let b = Shape::rect(v2(2.0, 4.0)).place(v2(-2.0, 12.0));
+    assert_eq!(a.contact_point(&b), v2(0.5, 10.5));
+}
+
+static TRIANGLE: [Vec2; 3] = [
+    Vec2 { x: -1.0, y: -1.0 },
+    Vec2 { x: 1.0, y: -1.0 },
+    Vec2 { x: -1.0, y: 1.0 },
+];
+
+#[test]
+fn test_polygon_bounds() {
+    let shape = Shape::polygon(&TRIANGLE).place(v2(3.0, 4.0));
+    assert_eq!(shape.kind(), ShapeKind::Polygon);
+    assert_eq!(shape.dims(), v2(2.0, 2.0));
+    assert_eq!(shape.min_x(), 2.0);
+    assert_eq!(shape.max_y(), 5.0);
+}
+
+#[test]
+#[should_panic]
+fn test_polygon_clockwise() {
+    static CLOCKWISE: [Vec2; 3] = [
+        Vec2 { x: -1.0, y: -1.0 },
+        Vec2 { x: -1.0, y: 1.0 },
+        Vec2 { x: 1.0, y: -1.0 },
+    ];
+    Shape::polygon(&CLOCKWISE);
+}
+
+#[test]
+fn test_interned_polygon() {
+    let loaded = TRIANGLE.to_vec();
+    let shape = Shape::interned_polygon(&loaded);
+    assert_eq!(shape, Shape::polygon(&TRIANGLE));
+    assert!(!std::ptr::eq(shape.vertices(), &TRIANGLE[..]));
+
+    // interning the same vertices again reuses the first copy
+    let reloaded = TRIANGLE.to_vec();
+    let other = Shape::interned_polygon(&reloaded);
+    assert!(std::ptr::eq(shape.vertices(), other.vertices()));
+}
+
+#[test]
+#[should_panic]
+fn test_interned_polygon_clockwise() {
+    let mut loaded = TRIANGLE.to_vec();
+    loaded.reverse();
+    Shape::interned_polygon(&loaded);
+}
+
+#[test]
+fn test_polygon_rect_normal() {
+    let sqrt2 = (2.0f64).sqrt();
+    let dst = Shape::polygon(&TRIANGLE).place(v2(0.0, 0.0));
+    let src = Shape::square(2.0).place(v2(1.0, 1.0));
+    let normal = dst.normal_from(&src);
+    assert!((normal.dir() - v2(-1.0, -1.0).normalize().unwrap()).len() < 1e-9);
+    assert!(normal.len().abs() < 1e-9);
+
+    // apart, the normal points between the nearest points
+    let src = Shape::square(2.0).place(v2(2.0, 2.0));
+    let normal = dst.normal_from(&src);
+    assert!((normal.dir() - v2(-1.0, -1.0).normalize().unwrap()).len() < 1e-9);
+    assert!((normal.len() + sqrt2).abs() < 1e-9);
+}
+
+#[test]
+fn test_polygon_circle_normal() {
+    let sqrt2 = (2.0f64).sqrt();
+    let dst = Shape::polygon(&TRIANGLE).place(v2(0.0, 0.0));
+    let src = Shape::circle(2.0).place(v2(1.0, 1.0));
+    let normal = dst.normal_from(&src);
+    assert!((normal.dir() - v2(-1.0, -1.0).normalize().unwrap()).len() < 1e-9);
+    assert!((normal.len() - (1.0 - sqrt2)).abs() < 1e-9);
+
+    // beyond a corner, the normal points away from the corner
+    let src = Shape::circle(2.0).place(v2(3.0, -1.0));
+    let normal = src.normal_from(&dst);
+    assert!((normal.dir() - v2(1.0, 0.0)).len() < 1e-9);
+    assert!((normal.len() + 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_polygon_contact() {
+    let a = Shape::polygon(&TRIANGLE).place(v2(0.0, 0.0));
+    let b = Shape::square(2.0).place(v2(0.0, -2.0));
+    assert!((a.contact_point(&b) - v2(0.0, -1.0)).len() < 1e-9);
+    assert!((b.contact_point(&a) - v2(0.0, -1.0)).len() < 1e-9);
+}
+
+#[test]
+fn test_ellipse_bounds() {
+    let shape = Shape::ellipse(v2(4.0, 2.0)).place(v2(1.0, 1.0));
+    assert_eq!(shape.kind(), ShapeKind::Circle);
+    assert_eq!(shape.min_x(), -1.0);
+    assert_eq!(shape.max_y(), 2.0);
+}
+
+#[test]
+fn test_ellipse_normal() {
+    let dst = Shape::ellipse(v2(4.0, 2.0)).place(v2(0.0, 0.0));
+    let src = Shape::circle(2.0).place(v2(2.5, 0.0));
+    let normal = dst.normal_from(&src);
+    assert!((normal.dir() - v2(-1.0, 0.0)).len() < 1e-9);
+    assert!((normal.len() - 0.5).abs() < 1e-9);
+    assert!((src.normal_from(&dst).len() - 0.5).abs() < 1e-9);
+
+    // apart, the length is the negated distance
+    let src = Shape::square(2.0).place(v2(0.0, 4.0));
+    let normal = dst.normal_from(&src);
+    assert!((normal.dir() - v2(0.0, -1.0)).len() < 1e-9);
+    assert!((normal.len() + 2.0).abs() < 1e-9);
+
+    // the normal is perpendicular to the boundary of the ellipse
+    let point = v2(2.0 * (0.5f64).sqrt(), (0.5f64).sqrt());
+    let outward = v2(point.x / 4.0, point.y).normalize().unwrap();
+    let src = Shape::circle(0.0).place(point + outward);
+    let normal = src.normal_from(&dst);
+    assert!((normal.dir() - outward).len() < 1e-6);
+    assert!((normal.len() + 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_masked_ellipse_normal() {
+    let dst = Shape::ellipse(v2(4.0, 2.0)).place(v2(0.0, 0.0));
+    let src = Shape::circle(2.0).place(v2(2.5, 0.0));
+    let mut mask = CardMask::full();
+    mask[Card::MinusX] = false;
+    let normal = dst.masked_normal_from(&src, mask);
+    assert!((normal.dir() - v2(0.0, -1.0)).len() < 1e-9);
+}
+
+#[test]
+fn test_ellipse_contact() {
+    let a = Shape::ellipse(v2(4.0, 2.0)).place(v2(0.0, 0.0));
+    let b = Shape::square(2.0).place(v2(0.0, 1.5));
+    assert!((a.contact_point(&b) - v2(0.0, 0.75)).len() < 1e-9);
+    assert!((b.contact_point(&a) - v2(0.0, 0.75)).len() < 1e-9);
+}
+
+#[test]
+fn test_half_plane_normal() {
+    let floor = Shape::half_plane(Card::PlusY.into()).place(v2(0.0, -1.0));
+    assert_eq!(floor.kind(), ShapeKind::HalfPlane);
+    let src = Shape::square(2.0).place(v2(5.0, -0.5));
+    let normal = floor.normal_from(&src);
+    assert!((normal.dir() - v2(0.0, -1.0)).len() < 1e-9);
+    assert!((normal.len() - 0.5).abs() < 1e-9);
+    let normal = src.normal_from(&floor);
+    assert!((normal.dir() - v2(0.0, 1.0)).len() < 1e-9);
+    assert!((normal.len() - 0.5).abs() < 1e-9);
+
+    // a tilted half-plane, apart from a circle
+    let wall = Shape::half_plane(v2(-1.0, 1.0)).place(v2(0.0, 0.0));
+    let src = Shape::circle(2.0).place(v2(-2.0, 2.0));
+    let normal = src.normal_from(&wall);
+    assert!((normal.dir() - v2(-1.0, 1.0).normalize().unwrap()).len() < 1e-9);
+    assert!((normal.len() - (1.0 - (8.0f64).sqrt())).abs() < 1e-9);
+}
+
+#[test]
+fn test_masked_half_plane_normal() {
+    let wall = Shape::half_plane(v2(-1.0, 1.0)).place(v2(0.0, 0.0));
+    let src = Shape::square(2.0).place(v2(0.0, 0.0));
+    let mut mask = CardMask::full();
+    mask[Card::MinusX] = false;
+    let normal = src.masked_normal_from(&wall, mask);
+    assert!((normal.dir() - v2(0.0, 1.0)).len() < 1e-9);
+    assert!((normal.len() - 2.0).abs() < 1e-9);
+}
+
+#[test]
+#[should_panic]
+fn test_half_plane_pair() {
+    let a = Shape::half_plane(Card::PlusY.into()).place(v2(0.0, 0.0));
+    let b = Shape::half_plane(Card::MinusY.into()).place(v2(0.0, 0.0));
+    a.normal_from(&b);
+}
+
+#[test]
+fn test_half_plane_contact() {
+    let floor = Shape::half_plane(Card::PlusY.into()).place(v2(0.0, 0.0));
+    let src = Shape::rect(v2(4.0, 2.0)).place(v2(3.0, 0.5));
+    assert!((floor.contact_point(&src) - v2(3.0, -0.25)).len() < 1e-9);
+    assert!((src.contact_point(&floor) - v2(3.0, -0.25)).len() < 1e-9);
+}
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cmp;
+
 // IndexRect represents a non-empty rectangular index range in a 2-D grid.
 
 #[derive(PartialEq, Eq, Copy, Clone)]
@@ -37,6 +39,38 @@ impl IndexRect {
     pub fn contains(self, val: (i32, i32)) -> bool {
         val.0 >= self.start.0 && val.0 < self.end.0 && val.1 >= self.start.1 && val.1 < self.end.1
     }
+
+    pub fn contains_rect(self, other: IndexRect) -> bool {
+        self.contains(other.start) && self.contains((other.end.0 - 1, other.end.1 - 1))
+    }
+
+    // returns the overlap of the two rects, or None if they do not overlap
+    pub fn intersect(self, other: IndexRect) -> Option<IndexRect> {
+        let start = (
+            cmp::max(self.start.0, other.start.0),
+            cmp::max(self.start.1, other.start.1),
+        );
+        let end = (
+            cmp::min(self.end.0, other.end.0),
+            cmp::min(self.end.1, other.end.1),
+        );
+        if start.0 < end.0 && start.1 < end.1 {
+            Some(IndexRect { start, end })
+        } else {
+            None
+        }
+    }
+
+    pub fn len(self) -> usize {
+        (self.end.0 - self.start.0) as usize * (self.end.1 - self.start.1) as usize
+    }
+
+    // returns the position of `val` in a row-major array of the elements
+    pub fn offset(self, val: (i32, i32)) -> usize {
+        debug_assert!(self.contains(val));
+        let width = (self.end.0 - self.start.0) as usize;
+        (val.1 - self.start.1) as usize * width + (val.0 - self.start.0) as usize
+    }
 }
 
 pub struct Iter {
@@ -72,6 +106,7 @@ impl Iterator for Iter {
 }
 
 #[cfg(test)]
+#[allow(clippy::manual_range_contains)]
 mod tests {
     use super::*;
     use std::collections::HashSet;
@@ -99,6 +134,19 @@ mod tests {
         assert!(!rect.contains((4, 7)));
     }
 
+    #[test]
+    fn test_intersect() {
+        let rect = IndexRect::new((2, 3), (5, 7));
+        let other = IndexRect::new((4, 0), (9, 4));
+        assert!(rect.intersect(other) == Some(IndexRect::new((4, 3), (5, 4))));
+        assert!(rect.intersect(IndexRect::new((5, 3), (6, 7))).is_none());
+        assert!(rect.contains_rect(IndexRect::new((3, 3), (5, 5))));
+        assert!(!rect.contains_rect(other));
+        assert_eq!(rect.len(), 12);
+        assert_eq!(rect.offset((2, 3)), 0);
+        assert_eq!(rect.offset((3, 4)), 4);
+    }
+
     #[test]
     #[should_panic]
     fn test_new_bad_x() {
@@ -20,10 +20,13 @@
 //! [Continuous collision detection](https://en.wikipedia.org/wiki/Collision_detection#A_posteriori_.28discrete.29_versus_a_priori_.28continuous.29),
 //! on the other hand, means that the time of collision is determined very
 //! precisely, and the user is not restricted to a fixed time-stepping method.
-//! There are currently two kinds of shapes supported by Collider: circles and
-//! rectangles. The user specifies the positions and velocities of these shapes,
-//! which they can update at any time, and Collider will solve for the precise
-//! times of collision and separation.
+//! There are currently five kinds of shapes supported by Collider: circles
+//! (which may be stretched into axis-aligned ellipses), axis-aligned
+//! rectangles, rectangles that may rotate, convex polygons, and unmoving
+//! half-planes for world boundaries.
+//! The user specifies the positions and velocities of these shapes, which they
+//! can update at any time, and Collider will solve for the precise times of
+//! collision and separation.
 //!
 //! There are certain advantages that continuous collision detection holds over
 //! the traditional approach. In a game engine, the position of a sprite may be
@@ -86,11 +89,15 @@
 //! ```
 
 extern crate fnv;
+#[cfg(feature = "parallel")]
+extern crate rayon;
 
+pub mod controller;
 mod core;
 mod float;
 pub mod geom;
 mod index_rect;
+pub mod response;
 #[cfg(test)]
 mod tests;
 mod util;
@@ -0,0 +1,256 @@
+// Copyright 2016-2018 Matthew D. Michelotti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Module containing helpers for responding to collisions, such as sliding
+//! along a wall, bouncing off of another hitbox, or pushing overlapping
+//! hitboxes apart.
+//!
+//! Collider itself only reports when hitboxes collide and separate. The
+//! functions in this module compute new velocities and positions from the
+//! current state of the hitboxes. New velocities may be applied using
+//! `Collider::set_hitbox_vel`, and overlapping hitboxes may be pushed apart
+//! with `resolve_penetration`.
+
+use crate::core::{Collider, HbId, HbProfile, HbVel, Hitbox};
+use crate::geom::{PlacedShape, Vec2};
+
+/// Describes how a hitbox is affected by collision responses.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Body {
+    /// A body that is never moved by a response, such as a wall.
+    Static,
+
+    /// A body with the given mass, which must be positive. When two dynamic
+    /// bodies collide, the lighter one is affected more.
+    Dynamic(f64),
+}
+
+impl Body {
+    fn inv_mass(self) -> f64 {
+        match self {
+            Body::Static => 0.0,
+            Body::Dynamic(mass) => {
+                assert!(mass > 0.0, "requires mass > 0.0");
+                1.0 / mass
+            }
+        }
+    }
+}
+
+/// The policy used to compute new velocities for a pair of colliding hitboxes.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Response {
+    /// Cancels all relative motion between the hitboxes, so they move together
+    /// afterwards.
+    Stop,
+
+    /// Cancels the relative motion along the collision normal, so the hitboxes
+    /// slide along each other's surface.
+    Slide,
+
+    /// Reflects the relative motion along the collision normal, scaled by the
+    /// given coefficient of restitution. A coefficient of `0.0` is equivalent
+    /// to `Slide`, and `1.0` is a perfectly elastic bounce.
+    Bounce(f64),
+}
+
+/// Computes the new velocities of two touching hitboxes according to
+/// `response`.
+///
+/// The collision normal is taken from `PlacedShape::normal_from`. If the
+/// hitboxes are not approaching each other along this normal, or if both
+/// bodies are static, then the velocities are returned unchanged. Only the
+/// `value` of each velocity is modified.
+pub fn collide_vels(
+    hitbox_1: &Hitbox,
+    body_1: Body,
+    hitbox_2: &Hitbox,
+    body_2: Body,
+    response: Response,
+) -> (HbVel, HbVel) {
+    let mut vel_1 = hitbox_1.vel.clone();
+    let mut vel_2 = hitbox_2.vel.clone();
+    let (inv_mass_1, inv_mass_2) = (body_1.inv_mass(), body_2.inv_mass());
+    let inv_mass_sum = inv_mass_1 + inv_mass_2;
+    if inv_mass_sum == 0.0 {
+        return (vel_1, vel_2);
+    }
+    let normal = hitbox_1.value.normal_from(&hitbox_2.value).dir();
+    let rel_vel = vel_1.value - vel_2.value;
+    let approach = rel_vel * normal;
+    if approach >= 0.0 {
+        return (vel_1, vel_2);
+    }
+    let impulse = match response {
+        Response::Stop => rel_vel * (-1.0 / inv_mass_sum),
+        Response::Slide => normal * (-approach / inv_mass_sum),
+        Response::Bounce(restitution) => {
+            assert!(restitution >= 0.0, "requires restitution >= 0.0");
+            normal * (-(1.0 + restitution) * approach / inv_mass_sum)
+        }
+    };
+    vel_1.value += impulse * inv_mass_1;
+    vel_2.value -= impulse * inv_mass_2;
+    (vel_1, vel_2)
+}
+
+/// Computes the offsets that two overlapping shapes should be moved by so
+/// that they are just touching.
+///
+/// The penetration depth and direction are taken from
+/// `PlacedShape::normal_from`, and the offsets are split between the shapes in
+/// proportion to their inverse masses. Returns zero offsets if the shapes are
+/// not overlapping or if both bodies are static.
+pub fn penetration_offsets(
+    shape_1: &PlacedShape,
+    body_1: Body,
+    shape_2: &PlacedShape,
+    body_2: Body,
+) -> (Vec2, Vec2) {
+    let (inv_mass_1, inv_mass_2) = (body_1.inv_mass(), body_2.inv_mass());
+    let inv_mass_sum = inv_mass_1 + inv_mass_2;
+    let normal = shape_1.normal_from(shape_2);
+    if inv_mass_sum == 0.0 || normal.len() <= 0.0 {
+        return (Vec2::zero(), Vec2::zero());
+    }
+    let offset = normal.dir() * (normal.len() / inv_mass_sum);
+    (offset * inv_mass_1, offset * -inv_mass_2)
+}
+
+/// Applies `response` to the hitboxes with ids `id_1` and `id_2`, typically
+/// after a `Collide` event between them.
+///
+/// The new velocities are computed by `collide_vels` from the current state of
+/// the hitboxes and set with `Collider::set_hitbox_vel`. The velocity of a
+/// `Body::Static` hitbox is left alone, so it may be attached to a parent.
+pub fn respond<P: HbProfile>(
+    collider: &mut Collider<P>,
+    id_1: HbId,
+    body_1: Body,
+    id_2: HbId,
+    body_2: Body,
+    response: Response,
+) {
+    let hitbox_1 = collider.get_hitbox(id_1);
+    let hitbox_2 = collider.get_hitbox(id_2);
+    let (vel_1, vel_2) = collide_vels(&hitbox_1, body_1, &hitbox_2, body_2, response);
+    if let Body::Dynamic(_) = body_1 {
+        collider.set_hitbox_vel(id_1, vel_1);
+    }
+    if let Body::Dynamic(_) = body_2 {
+        collider.set_hitbox_vel(id_2, vel_2);
+    }
+}
+
+/// Pushes apart the hitboxes with ids `id_1` and `id_2` if they are already
+/// overlapping, so that they are just touching.
+///
+/// This is useful for overlaps that were not prevented by a collision
+/// response, such as hitboxes that were added on top of each other. The
+/// offsets are computed by `penetration_offsets`, and only `Body::Dynamic`
+/// hitboxes are moved. Velocities are not changed.
+pub fn resolve_penetration<P: HbProfile>(
+    collider: &mut Collider<P>,
+    id_1: HbId,
+    body_1: Body,
+    id_2: HbId,
+    body_2: Body,
+) {
+    let shape_1 = collider.get_hitbox(id_1).value;
+    let shape_2 = collider.get_hitbox(id_2).value;
+    let (offset_1, offset_2) = penetration_offsets(&shape_1, body_1, &shape_2, body_2);
+    if let Body::Dynamic(_) = body_1 {
+        collider.set_hitbox_pos(id_1, shape_1.pos + offset_1);
+    }
+    if let Body::Dynamic(_) = body_2 {
+        collider.set_hitbox_pos(id_2, shape_2.pos + offset_2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::{v2, Shape};
+
+    #[test]
+    fn test_slide_against_wall() {
+        let mover = Shape::square(2.0).place(v2(0.0, 2.0)).moving(v2(3.0, -4.0));
+        let wall = Shape::rect(v2(10.0, 2.0)).place(v2(0.0, 0.0)).still();
+        let (vel_1, vel_2) = collide_vels(
+            &mover,
+            Body::Dynamic(1.0),
+            &wall,
+            Body::Static,
+            Response::Slide,
+        );
+        assert_eq!(vel_1.value, v2(3.0, 0.0));
+        assert_eq!(vel_2.value, v2(0.0, 0.0));
+        let (vel_1, _) = collide_vels(
+            &mover,
+            Body::Dynamic(1.0),
+            &wall,
+            Body::Static,
+            Response::Stop,
+        );
+        assert_eq!(vel_1.value, v2(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_bounce() {
+        let hitbox_1 = Shape::circle(2.0).place(v2(-2.0, 0.0)).moving(v2(1.0, 0.0));
+        let hitbox_2 = Shape::circle(2.0).place(v2(0.0, 0.0)).moving(v2(-1.0, 0.0));
+        let body = Body::Dynamic(2.0);
+        let (vel_1, vel_2) = collide_vels(&hitbox_1, body, &hitbox_2, body, Response::Bounce(1.0));
+        assert_eq!(vel_1.value, v2(-1.0, 0.0));
+        assert_eq!(vel_2.value, v2(1.0, 0.0));
+        let (vel_1, vel_2) = collide_vels(&hitbox_1, body, &hitbox_2, body, Response::Bounce(0.5));
+        assert_eq!(vel_1.value, v2(-0.5, 0.0));
+        assert_eq!(vel_2.value, v2(0.5, 0.0));
+
+        // hitboxes that are already moving apart are unaffected
+        let separating_1 = Shape::circle(2.0)
+            .place(v2(-2.0, 0.0))
+            .moving(v2(-1.0, 0.0));
+        let separating_2 = Shape::circle(2.0).place(v2(0.0, 0.0)).moving(v2(1.0, 0.0));
+        let (vel_1, vel_2) = collide_vels(
+            &separating_1,
+            body,
+            &separating_2,
+            body,
+            Response::Bounce(1.0),
+        );
+        assert_eq!(vel_1.value, v2(-1.0, 0.0));
+        assert_eq!(vel_2.value, v2(1.0, 0.0));
+    }
+
+    #[test]
+    fn test_penetration_offsets() {
+        let shape_1 = Shape::square(2.0).place(v2(1.5, 0.0));
+        let shape_2 = Shape::square(2.0).place(v2(0.0, 0.0));
+        let (offset_1, offset_2) =
+            penetration_offsets(&shape_1, Body::Dynamic(1.0), &shape_2, Body::Dynamic(3.0));
+        assert_eq!(offset_1, v2(0.375, 0.0));
+        assert_eq!(offset_2, v2(-0.125, 0.0));
+        let (offset_1, offset_2) =
+            penetration_offsets(&shape_1, Body::Static, &shape_2, Body::Dynamic(3.0));
+        assert_eq!(offset_1, v2(0.0, 0.0));
+        assert_eq!(offset_2, v2(-0.5, 0.0));
+
+        let shape_1 = Shape::square(2.0).place(v2(3.0, 0.0));
+        assert_eq!(
+            penetration_offsets(&shape_1, Body::Dynamic(1.0), &shape_2, Body::Dynamic(1.0)),
+            (v2(0.0, 0.0), v2(0.0, 0.0))
+        );
+    }
+}
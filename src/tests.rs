@@ -12,8 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{Collider, HbEvent, HbId, HbProfile, HbVel};
-use crate::geom::{v2, Shape};
+use super::controller::CharacterController;
+use super::response::{self, Body, Response};
+use super::{Collider, HbEvent, HbId, HbProfile, HbVel, TileLayer};
+use crate::geom::{v2, Card, CardMask, Shape, Vec2};
 use std::f64;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
@@ -36,12 +38,12 @@ impl HbProfile for TestHbProfile {
     }
 }
 
-fn advance_to_event(collider: &mut Collider<TestHbProfile>, time: f64) {
+fn advance_to_event<P: HbProfile>(collider: &mut Collider<P>, time: f64) {
     advance(collider, time);
     assert_eq!(collider.next_time(), collider.time());
 }
 
-fn advance(collider: &mut Collider<TestHbProfile>, time: f64) {
+fn advance<P: HbProfile>(collider: &mut Collider<P>, time: f64) {
     while collider.time() < time {
         assert!(collider.next().is_none());
         let new_time = collider.next_time().min(time);
@@ -50,7 +52,7 @@ fn advance(collider: &mut Collider<TestHbProfile>, time: f64) {
     assert_eq!(collider.time(), time);
 }
 
-fn advance_through_events(collider: &mut Collider<TestHbProfile>, time: f64) {
+fn advance_through_events<P: HbProfile>(collider: &mut Collider<P>, time: f64) {
     while collider.time() < time {
         collider.next();
         let new_time = collider.next_time().min(time);
@@ -281,4 +283,1348 @@ fn test_separate_initial_overlap() {
     advance(&mut collider, 1.5);
 }
 
+#[test]
+fn test_tile_layer_events() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+
+    let mut tiles = TileLayer::new(v2(0.0, 0.0), 1.0, 10, 4, 0);
+    for x in 0..10 {
+        tiles.set_solid((x, 0), true);
+    }
+    collider.set_tile_layer(tiles, 100.into());
+
+    collider.add_hitbox(
+        0.into(),
+        Shape::square(0.5)
+            .place(v2(2.5, 3.25))
+            .moving(v2(0.0, -1.0)),
+    );
+
+    advance_to_event(&mut collider, 2.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::TileCollide((2, 0)), 0.into(), 100.into()))
+    );
+    assert_eq!(collider.next(), None);
+    assert_eq!(collider.get_tile_contact(0), Some((2, 0)));
+
+    // sliding across the seams between tiles should not generate events
+    collider.set_hitbox_vel(0, HbVel::moving(v2(1.0, 0.0)));
+    advance_to_event(&mut collider, 10.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::TileSeparate((9, 0)), 0.into(), 100.into()))
+    );
+    assert_eq!(collider.next(), None);
+    assert_eq!(collider.get_tile_contact(0), None);
+
+    advance(&mut collider, 20.0);
+}
+
+#[test]
+fn test_simultaneous_tile_events() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    let mut tiles = TileLayer::new(v2(0.0, 0.0), 1.0, 10, 4, 0);
+    for x in 0..10 {
+        tiles.set_solid((x, 0), true);
+    }
+    collider.set_tile_layer(tiles, 100.into());
+    for (id, x) in [(0, 2.5), (1, 7.5)].iter().cloned() {
+        collider.add_hitbox(
+            id.into(),
+            Shape::square(0.5).place(v2(x, 3.25)).moving(v2(0.0, -1.0)),
+        );
+    }
+
+    // the events are gathered before any of them is handled
+    advance_to_event(&mut collider, 2.0);
+    let mut events = Vec::new();
+    while let Some(event) = collider.next() {
+        events.push(event);
+    }
+    events.sort_by_key(|&(_, profile, _)| profile);
+    assert_eq!(
+        events,
+        vec![
+            (HbEvent::TileCollide((2, 0)), 0.into(), 100.into()),
+            (HbEvent::TileCollide((7, 0)), 1.into(), 100.into()),
+        ]
+    );
+}
+
+#[test]
+fn test_set_tile_solid() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.set_tile_layer(TileLayer::new(v2(0.0, 0.0), 1.0, 4, 4, 0), 100.into());
+
+    collider.add_hitbox(0.into(), Shape::square(0.5).place(v2(1.5, 1.25)).still());
+    assert_eq!(collider.next_time(), f64::INFINITY);
+
+    collider.set_tile_solid((1, 0), true);
+    advance_to_event(&mut collider, 0.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::TileCollide((1, 0)), 0.into(), 100.into()))
+    );
+
+    collider.set_tile_solid((1, 0), false);
+    advance_to_event(&mut collider, 0.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::TileSeparate((1, 0)), 0.into(), 100.into()))
+    );
+    assert_eq!(collider.next_time(), f64::INFINITY);
+}
+
+#[test]
+fn test_add_static_rects() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+
+    let walls: Vec<_> = (0..8)
+        .map(|x| Shape::square(1.0).place(v2(x as f64 + 0.5, 0.5)))
+        .collect();
+    let mut next_id = 10;
+    let profiles = collider.add_static_rects(&walls, |_| {
+        next_id += 1;
+        next_id.into()
+    });
+    assert_eq!(profiles, vec![11.into()]);
+    assert_eq!(
+        collider.get_hitbox(11).value,
+        Shape::rect(v2(8.0, 1.0)).place(v2(4.0, 0.5))
+    );
+
+    collider.add_hitbox(
+        0.into(),
+        Shape::square(1.0).place(v2(0.5, 1.5)).moving(v2(1.0, 0.0)),
+    );
+    advance_through_events(&mut collider, 0.0);
+    assert!(collider.is_overlapping(0, 11));
+    advance(&mut collider, 7.0);
+    advance_to_event(&mut collider, 8.25);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Separate, 0.into(), 11.into()))
+    );
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct PlatformHbProfile {
+    id: HbId,
+    solid_sides: CardMask,
+}
+
+impl HbProfile for PlatformHbProfile {
+    fn id(&self) -> HbId {
+        self.id
+    }
+    fn solid_sides(&self) -> CardMask {
+        self.solid_sides
+    }
+    fn can_interact(&self, _other: &PlatformHbProfile) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_one_way_platform() {
+    let mut collider = Collider::<PlatformHbProfile>::new(4.0, 0.25);
+
+    let platform = PlatformHbProfile {
+        id: 0,
+        solid_sides: Card::PlusY.into(),
+    };
+    let player = PlatformHbProfile {
+        id: 1,
+        solid_sides: CardMask::full(),
+    };
+    collider.add_hitbox(
+        platform,
+        Shape::rect(v2(4.0, 1.0)).place(v2(0.0, 0.0)).still(),
+    );
+    collider.add_hitbox(
+        player,
+        Shape::square(1.0).place(v2(0.0, -3.0)).moving(v2(0.0, 1.0)),
+    );
+
+    // jumping up through the platform is ignored
+    advance(&mut collider, 3.0);
+    assert!(!collider.is_overlapping(0, 1));
+    assert_eq!(collider.get_overlaps(1), vec![]);
+    advance(&mut collider, 5.0);
+
+    // landing on top of the platform is a collision
+    collider.set_hitbox_vel(1, HbVel::moving(v2(0.0, -1.0)));
+    advance_to_event(&mut collider, 6.0);
+    assert_eq!(collider.next(), Some((HbEvent::Collide, platform, player)));
+    collider.set_hitbox_vel(1, HbVel::still());
+    advance(&mut collider, 10.0);
+    assert!(collider.is_overlapping(0, 1));
+}
+
+#[test]
+fn test_one_way_platform_initial_overlap() {
+    let mut collider = Collider::<PlatformHbProfile>::new(4.0, 0.25);
+
+    let platform = PlatformHbProfile {
+        id: 0,
+        solid_sides: Card::PlusY.into(),
+    };
+    let player = PlatformHbProfile {
+        id: 1,
+        solid_sides: CardMask::full(),
+    };
+    collider.add_hitbox(
+        platform,
+        Shape::rect(v2(4.0, 1.0)).place(v2(0.0, 0.0)).still(),
+    );
+    let overlaps = collider.add_hitbox(
+        player,
+        Shape::square(1.0).place(v2(1.0, 0.0)).moving(v2(1.0, 0.0)),
+    );
+    assert_eq!(overlaps, vec![]);
+
+    // leaving sideways and coming back from above is a collision
+    advance(&mut collider, 4.0);
+    collider.set_hitbox_vel(1, HbVel::moving(v2(0.0, 1.0)));
+    advance(&mut collider, 6.0);
+    assert_eq!(collider.get_hitbox(1).value.pos, v2(5.0, 2.0));
+    collider.set_hitbox_vel(1, HbVel::moving(v2(-2.0, -0.5)));
+    advance_to_event(&mut collider, 8.0);
+    assert_eq!(collider.next(), Some((HbEvent::Collide, platform, player)));
+}
+
+#[test]
+fn test_set_hitbox_pos() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(0.into(), Shape::square(1.).place(v2(0., 0.)).still());
+    collider.add_hitbox(1.into(), Shape::square(1.).place(v2(10., 0.)).still());
+
+    advance(&mut collider, 1.0);
+    collider.set_hitbox_pos(0, v2(9.5, 0.5));
+    assert_eq!(collider.get_hitbox(0).value.pos, v2(9.5, 0.5));
+    advance_to_event(&mut collider, 1.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 1.into()))
+    );
+
+    advance(&mut collider, 2.0);
+    collider.set_hitbox_pos(0, v2(0., 0.));
+    advance_to_event(&mut collider, 2.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Separate, 0.into(), 1.into()))
+    );
+    advance(&mut collider, 3.0);
+}
+
+#[test]
+fn test_response_helpers() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(
+        0.into(),
+        Shape::square(1.).place(v2(0., 0.)).moving(v2(1., 1.)),
+    );
+    collider.add_hitbox(1.into(), Shape::rect(v2(1., 10.)).place(v2(3., 0.)).still());
+
+    advance_to_event(&mut collider, 2.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 1.into()))
+    );
+    response::respond(
+        &mut collider,
+        0,
+        Body::Dynamic(1.0),
+        1,
+        Body::Static,
+        Response::Slide,
+    );
+    assert_eq!(collider.get_hitbox(0).vel.value, v2(0., 1.));
+    assert_eq!(collider.get_hitbox(1).vel.value, v2(0., 0.));
+
+    collider.set_hitbox_pos(0, v2(2.5, 2.));
+    response::resolve_penetration(&mut collider, 0, Body::Dynamic(1.0), 1, Body::Static);
+    assert_eq!(collider.get_hitbox(0).value.pos, v2(2., 2.));
+    assert_eq!(collider.get_hitbox(1).value.pos, v2(3., 0.));
+
+    advance_to_event(&mut collider, 5.75);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Separate, 0.into(), 1.into()))
+    );
+}
+
+#[test]
+fn test_respond_attached_static() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(
+        0.into(),
+        Shape::square(1.).place(v2(0., 0.)).moving(v2(1., 0.)),
+    );
+    collider.add_hitbox(1.into(), Shape::square(1.).place(v2(10., 0.)).still());
+    collider.add_hitbox(2.into(), Shape::rect(v2(1., 10.)).place(v2(3., 0.)).still());
+    collider.attach_hitbox(2, 1, v2(-7., 0.));
+
+    advance_to_event(&mut collider, 2.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 2.into()))
+    );
+    response::respond(
+        &mut collider,
+        0,
+        Body::Dynamic(1.0),
+        2,
+        Body::Static,
+        Response::Stop,
+    );
+    assert_eq!(collider.get_hitbox(0).vel.value, v2(0., 0.));
+
+    collider.set_hitbox_pos(0, v2(2.5, 0.));
+    response::resolve_penetration(&mut collider, 0, Body::Dynamic(1.0), 2, Body::Static);
+    assert_eq!(collider.get_hitbox(0).value.pos, v2(2., 0.));
+    assert_eq!(collider.get_hitbox(2).value.pos, v2(3., 0.));
+    advance(&mut collider, 3.0);
+}
+
+#[test]
+fn test_reused_storage() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(5.into(), Shape::square(1.).place(v2(0., 0.)).still());
+    collider.add_hitbox(
+        3.into(),
+        Shape::square(1.).place(v2(-10., 0.)).moving(v2(1., 0.)),
+    );
+    assert_eq!(collider.remove_hitbox(5), vec![]);
+    collider.add_hitbox(
+        7.into(),
+        Shape::square(1.).place(v2(10., 0.)).moving(v2(-1., 0.)),
+    );
+
+    advance_to_event(&mut collider, 9.5);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 3.into(), 7.into()))
+    );
+    assert!(collider.is_overlapping(7, 3));
+    assert!(!collider.is_overlapping(5, 3));
+}
+
+#[test]
+#[should_panic]
+fn test_duplicate_id() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(0.into(), Shape::square(1.).place(v2(0., 0.)).still());
+    collider.add_hitbox(0.into(), Shape::square(1.).place(v2(5., 0.)).still());
+}
+
+#[test]
+fn test_batch_add_remove() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(0.into(), Shape::square(2.).place(v2(0., 0.)).still());
+
+    let overlaps = collider.add_hitboxes(vec![
+        (1.into(), Shape::square(1.).place(v2(1., 0.)).still()),
+        (2.into(), Shape::circle(1.).place(v2(1.75, 0.)).still()),
+        (
+            3.into(),
+            Shape::square(1.).place(v2(10., 0.)).moving(v2(-1., 0.)),
+        ),
+    ]);
+    assert_eq!(overlaps.len(), 3);
+    assert_eq!(sorted_ids(&overlaps[&1]), vec![0, 2]);
+    assert_eq!(sorted_ids(&overlaps[&2]), vec![1]);
+    assert_eq!(sorted_ids(&overlaps[&3]), vec![]);
+    assert!(collider.is_overlapping(0, 1));
+    assert!(collider.is_overlapping(2, 1));
+
+    advance_to_event(&mut collider, 7.25);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 2.into(), 3.into()))
+    );
+
+    let separated = collider.remove_hitboxes(vec![1, 3]);
+    assert_eq!(separated.len(), 2);
+    assert_eq!(sorted_ids(&separated[&1]), vec![0, 2]);
+    assert_eq!(sorted_ids(&separated[&3]), vec![2]);
+    assert_eq!(collider.get_overlaps(2), vec![]);
+    advance(&mut collider, 20.0);
+}
+
+#[test]
+fn test_batch_set_vels() {
+    // enough hitboxes that the pairs are solved in parallel when enabled
+    let mut sequential = Collider::<TestHbProfile>::new(4.0, 0.25);
+    let mut batched = Collider::<TestHbProfile>::new(4.0, 0.25);
+    for id in 0..100 {
+        let pos = v2((id % 10) as f64 * 3.0, (id / 10) as f64 * 3.0);
+        let hitbox = Shape::square(2.0).place(pos).still();
+        sequential.add_hitbox(id.into(), hitbox.clone());
+        batched.add_hitbox(id.into(), hitbox);
+    }
+
+    let vels: Vec<(HbId, HbVel)> = (0..100)
+        .map(|id| {
+            let dir = if id % 2 == 0 { 1.0 } else { -1.0 };
+            (id, HbVel::moving(v2(dir, dir * 0.5)))
+        })
+        .collect();
+    for (id, vel) in vels.iter().cloned() {
+        sequential.set_hitbox_vel(id, vel);
+    }
+    // repeated ids take their last velocity
+    batched.set_hitbox_vels(vec![(7, HbVel::moving(v2(5.0, 0.0)))]);
+    batched.set_hitbox_vels(
+        vec![(3, HbVel::moving(v2(-4.0, 1.0)))]
+            .into_iter()
+            .chain(vels),
+    );
+
+    let events = all_events(&mut sequential, 20.0);
+    assert!(events.len() > 100);
+    assert_eq!(events, all_events(&mut batched, 20.0));
+}
+
+#[test]
+fn test_bounded_grid() {
+    // the bounds only cover part of the scene, so some hitboxes overflow
+    let bounds = Shape::rect(v2(20.0, 20.0)).place(v2(10.0, 10.0));
+    let mut sparse = Collider::<TestHbProfile>::new(4.0, 0.25);
+    let mut dense = Collider::<TestHbProfile>::with_bounds(4.0, 0.25, bounds);
+    assert_eq!(dense.bounds(), Some(bounds));
+    for id in 0..64 {
+        let pos = v2((id % 8) as f64 * 3.0, (id / 8) as f64 * 3.0);
+        let shape = if id % 5 == 0 {
+            Shape::rect(v2(9.0, 2.0))
+        } else {
+            Shape::circle(2.0)
+        };
+        let dir = if id % 2 == 0 { 1.0 } else { -1.0 };
+        let hitbox = shape.place(pos).moving(v2(dir, dir * 0.75));
+        sparse.add_hitbox(id.into(), hitbox.clone());
+        dense.add_hitbox(id.into(), hitbox);
+    }
+    let events = all_events(&mut sparse, 30.0);
+    assert!(events.len() > 50);
+    assert_eq!(events, all_events(&mut dense, 30.0));
+}
+
+#[test]
+fn test_mixed_hitbox_sizes() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    let bullet = Shape::circle(0.5)
+        .place(v2(-50.0, 0.0))
+        .moving(v2(10.0, 0.0));
+    collider.add_hitbox(0.into(), bullet);
+    let wall = Shape::rect(v2(20.0, 100.0)).place(v2(0.0, 0.0)).still();
+    collider.add_hitbox(1.into(), wall);
+    let boss = Shape::rect(v2(40.0, 40.0))
+        .place(v2(80.0, 0.0))
+        .moving(v2(-5.0, 0.0));
+    collider.add_hitbox(2.into(), boss);
+
+    let mut events = Vec::new();
+    let mut steps = 0;
+    while collider.time() < 9.5 {
+        while let Some((event, profile_1, profile_2)) = collider.next() {
+            events.push((collider.time(), event, sort(vec![profile_1, profile_2])));
+        }
+        let new_time = collider.next_time().min(9.5);
+        collider.set_time(new_time);
+        steps += 1;
+    }
+
+    let expected = [
+        (3.975, HbEvent::Collide, vec![0.into(), 1.into()]),
+        (6.05, HbEvent::Separate, vec![0.into(), 1.into()]),
+        (109.75 / 15.0, HbEvent::Collide, vec![0.into(), 2.into()]),
+    ];
+    assert_eq!(events.len(), expected.len());
+    for ((time, event, ids), (exp_time, exp_event, exp_ids)) in events.iter().zip(expected) {
+        assert!((time - exp_time).abs() < 1e-9);
+        assert_eq!((*event, ids), (exp_event, &exp_ids));
+    }
+    // the bullet reiterates once per cell it crosses, while the wall and the
+    // boss are filed at coarse levels and do not reiterate in this time
+    assert!(steps <= 30, "{} steps", steps);
+}
+
+#[test]
+fn test_out_of_bounds_events() {
+    let bounds = Shape::rect(v2(20.0, 20.0)).place(v2(10.0, 10.0));
+    let mut collider = Collider::<TestHbProfile>::with_bounds(4.0, 0.25, bounds);
+    collider.add_hitbox(
+        0.into(),
+        Shape::square(2.0)
+            .place(v2(10.0, 10.0))
+            .moving(v2(1.0, 0.0)),
+    );
+    collider.add_hitbox(1.into(), Shape::square(2.0).place(v2(-5.0, 10.0)).still());
+    advance(&mut collider, 1.0);
+    assert_eq!(collider.next_bounds_event(), None);
+
+    collider.set_out_of_bounds_events(true);
+    assert!(collider.next().is_none());
+    assert_eq!(collider.next_bounds_event(), Some(1.into()));
+    assert_eq!(collider.next_bounds_event(), None);
+
+    advance_to_event(&mut collider, 9.0);
+    assert!(collider.next().is_none());
+    assert_eq!(collider.next_bounds_event(), Some(0.into()));
+
+    // reported again only after returning inside the bounds
+    collider.set_hitbox_vel(0, HbVel::moving(v2(-1.0, 0.0)));
+    advance(&mut collider, 12.0);
+    collider.set_hitbox_vel(0, HbVel::moving(v2(0.0, 1.0)));
+    advance_to_event(&mut collider, 21.0);
+    assert!(collider.next().is_none());
+    assert_eq!(collider.next_bounds_event(), Some(0.into()));
+    advance(&mut collider, 30.0);
+    assert_eq!(collider.next_bounds_event(), None);
+}
+
+#[test]
+fn test_rebase_time() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(
+        0.into(),
+        Shape::square(2.0)
+            .place(v2(-10.0, 0.0))
+            .moving_until(v2(1.0, 0.0), 50.0),
+    );
+    collider.add_hitbox(
+        1.into(),
+        Shape::circle(2.0)
+            .place(v2(10.0, 0.0))
+            .moving(v2(-1.0, 0.0)),
+    );
+    advance(&mut collider, 4.0);
+    collider.rebase_time(1000.0);
+    assert_eq!(collider.time(), -996.0);
+    assert_eq!(collider.get_hitbox(0).value.pos, v2(-6.0, 0.0));
+    assert_eq!(collider.get_hitbox(0).vel.end_time, -950.0);
+
+    advance_to_event(&mut collider, -991.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 1.into()))
+    );
+    collider.rebase_time(-991.0);
+    assert_eq!(collider.time(), 0.0);
+    advance_to_event(&mut collider, 2.125);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Separate, 0.into(), 1.into()))
+    );
+    collider.remove_hitbox(1);
+    collider.set_hitbox_vel(0, HbVel::still());
+    advance(&mut collider, 100.0);
+}
+
+#[test]
+fn test_rebase_large_offset() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    advance(&mut collider, 9e49);
+    // the collision is too far away to be queued until the time is rebased
+    collider.add_hitbox(
+        0.into(),
+        Shape::square(1.0)
+            .place(v2(-2.0, 0.0))
+            .moving(v2(1e-49, 0.0)),
+    );
+    collider.add_hitbox(1.into(), Shape::square(1.0).place(v2(0.5, 0.0)).still());
+    assert_eq!(collider.next_time(), f64::INFINITY);
+
+    collider.rebase_time(9e49);
+    assert_eq!(collider.time(), 0.0);
+    advance(&mut collider, 1.4e49);
+    let time = collider.next_time();
+    assert!((time - 1.5e49).abs() < 1e40);
+    collider.set_time(time);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 1.into()))
+    );
+}
+
+#[test]
+fn test_shift_origin() {
+    let bounds = Shape::rect(v2(40.0, 20.0)).place(v2(0.0, 5.0));
+    let mut collider = Collider::<TestHbProfile>::with_bounds(4.0, 0.25, bounds);
+    let mut tiles = TileLayer::new(v2(-20.0, -4.0), 1.0, 40, 4, 0);
+    for x in 0..40 {
+        tiles.set_solid((x, 0), true);
+    }
+    collider.set_tile_layer(tiles, 100.into());
+    collider.add_hitbox(
+        0.into(),
+        Shape::square(2.0)
+            .place(v2(-10.0, 0.0))
+            .moving(v2(1.0, 0.0)),
+    );
+    collider.add_hitbox(
+        1.into(),
+        Shape::circle(2.0)
+            .place(v2(10.0, 0.0))
+            .moving(v2(-1.0, 0.0)),
+    );
+    collider.add_hitbox(
+        2.into(),
+        Shape::square(0.5)
+            .place(v2(2.5, -0.75))
+            .moving(v2(0.0, -1.0)),
+    );
+    advance(&mut collider, 1.0);
+
+    let delta = v2(1000.5, -250.0);
+    collider.shift_origin(delta);
+    assert_eq!(collider.get_hitbox(0).value.pos, v2(-9.0, 0.0) - delta);
+    assert_eq!(
+        collider.tile_layer().unwrap().origin(),
+        v2(-20.0, -4.0) - delta
+    );
+    assert_eq!(collider.bounds().unwrap().pos, v2(0.0, 5.0) - delta);
+    let query = Shape::square(1.0).place(v2(9.0, 0.0) - delta);
+    assert_eq!(collider.query_overlaps(&query, &0.into()), vec![1.into()]);
+
+    advance_to_event(&mut collider, 2.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::TileCollide((22, 0)), 2.into(), 100.into()))
+    );
+    advance_to_event(&mut collider, 3.75);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::TileSeparate((23, 0)), 2.into(), 100.into()))
+    );
+    advance_to_event(&mut collider, 9.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 1.into()))
+    );
+    collider.remove_hitbox(2);
+    advance_to_event(&mut collider, 11.125);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Separate, 0.into(), 1.into()))
+    );
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct SensorHbProfile {
+    id: HbId,
+    margin: Option<f64>,
+}
+
+impl HbProfile for SensorHbProfile {
+    fn id(&self) -> HbId {
+        self.id
+    }
+    fn sensor_margin(&self) -> Option<f64> {
+        self.margin
+    }
+    fn can_interact(&self, _other: &SensorHbProfile) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_proximity_events() {
+    let mut collider = Collider::<SensorHbProfile>::new(4.0, 0.25);
+    let sensor = SensorHbProfile {
+        id: 0,
+        margin: Some(3.0),
+    };
+    let mover = SensorHbProfile {
+        id: 1,
+        margin: None,
+    };
+    let bystander = SensorHbProfile {
+        id: 2,
+        margin: None,
+    };
+    collider.add_hitbox(sensor, Shape::square(2.0).place(v2(0.0, 0.0)).still());
+    collider.add_hitbox(
+        mover,
+        Shape::square(2.0)
+            .place(v2(10.0, 0.0))
+            .moving(v2(-1.0, 0.0)),
+    );
+    collider.add_hitbox(bystander, Shape::square(2.0).place(v2(10.0, -5.0)).still());
+
+    advance_to_event(&mut collider, 5.0);
+    assert_eq!(collider.next(), Some((HbEvent::Enter, sensor, mover)));
+    assert_eq!(collider.next(), None);
+    advance_to_event(&mut collider, 8.0);
+    assert_eq!(collider.next(), Some((HbEvent::Collide, sensor, mover)));
+    assert_eq!(collider.next(), None);
+
+    // the pair stays in proximity after separating
+    collider.set_hitbox_vel(1, HbVel::moving(v2(1.0, 0.0)));
+    advance_to_event(&mut collider, 8.25);
+    assert_eq!(collider.next(), Some((HbEvent::Separate, sensor, mover)));
+    assert_eq!(collider.next(), None);
+    advance_to_event(&mut collider, 11.25);
+    assert_eq!(collider.next(), Some((HbEvent::Exit, sensor, mover)));
+    assert_eq!(collider.next(), None);
+
+    // hitboxes added within the margin enter at once, and removal is silent
+    collider.remove_hitbox(1);
+    let nearby = SensorHbProfile {
+        id: 3,
+        margin: None,
+    };
+    collider.add_hitbox(nearby, Shape::circle(1.0).place(v2(0.0, 3.0)).still());
+    assert_eq!(collider.next(), Some((HbEvent::Enter, sensor, nearby)));
+    assert_eq!(collider.next(), None);
+    collider.remove_hitbox(0);
+    advance(&mut collider, 20.0);
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct ZoneHbProfile {
+    id: HbId,
+    zone: bool,
+}
+
+impl HbProfile for ZoneHbProfile {
+    fn id(&self) -> HbId {
+        self.id
+    }
+    fn is_container(&self) -> bool {
+        self.zone
+    }
+    fn can_interact(&self, _other: &ZoneHbProfile) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_containment_events() {
+    let mut collider = Collider::<ZoneHbProfile>::new(4.0, 0.25);
+    let water = ZoneHbProfile { id: 5, zone: true };
+    let swimmer = ZoneHbProfile { id: 1, zone: false };
+    collider.add_hitbox(
+        water,
+        Shape::rect(v2(10.0, 4.0)).place(v2(0.0, 0.0)).still(),
+    );
+    collider.add_hitbox(
+        swimmer,
+        Shape::square(2.0)
+            .place(v2(-10.0, 0.0))
+            .moving(v2(1.0, 0.0)),
+    );
+
+    advance_to_event(&mut collider, 4.0);
+    assert_eq!(collider.next(), Some((HbEvent::Collide, swimmer, water)));
+    assert_eq!(collider.next(), None);
+    advance_to_event(&mut collider, 6.0);
+    assert_eq!(collider.next(), Some((HbEvent::Contain, water, swimmer)));
+    assert_eq!(collider.next(), None);
+    advance_to_event(&mut collider, 14.25);
+    assert_eq!(collider.next(), Some((HbEvent::Uncontain, water, swimmer)));
+    assert_eq!(collider.next(), None);
+    advance_to_event(&mut collider, 16.25);
+    assert_eq!(collider.next(), Some((HbEvent::Separate, swimmer, water)));
+    assert_eq!(collider.next(), None);
+
+    // hitboxes added inside of a container are reported at once
+    let diver = ZoneHbProfile { id: 2, zone: false };
+    collider.add_hitbox(diver, Shape::circle(1.0).place(v2(1.0, 0.0)).still());
+    assert_eq!(collider.next(), Some((HbEvent::Contain, water, diver)));
+    assert_eq!(collider.next(), None);
+    collider.remove_hitbox(5);
+    advance(&mut collider, 30.0);
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct CombatHbProfile {
+    id: HbId,
+    combat: bool,
+}
+
+impl HbProfile for CombatHbProfile {
+    fn id(&self) -> HbId {
+        self.id
+    }
+    fn collide_depth(&self, other: &CombatHbProfile) -> f64 {
+        if self.combat && other.combat {
+            1.0
+        } else {
+            0.0
+        }
+    }
+    fn separate_depth(&self, other: &CombatHbProfile) -> f64 {
+        self.collide_depth(other) * 0.5
+    }
+    fn can_interact(&self, _other: &CombatHbProfile) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_collide_depth() {
+    let mut collider = Collider::<CombatHbProfile>::new(4.0, 0.25);
+    let attack = CombatHbProfile {
+        id: 0,
+        combat: true,
+    };
+    let hurtbox = CombatHbProfile {
+        id: 1,
+        combat: true,
+    };
+    let wall = CombatHbProfile {
+        id: 2,
+        combat: false,
+    };
+    collider.add_hitbox(
+        attack,
+        Shape::square(2.0)
+            .place(v2(-10.0, 0.0))
+            .moving(v2(1.0, 0.0)),
+    );
+    collider.add_hitbox(hurtbox, Shape::square(2.0).place(v2(0.0, 0.0)).still());
+    collider.add_hitbox(wall, Shape::square(2.0).place(v2(-5.0, 2.0)).still());
+
+    // grazing the wall is a collision, but grazing the hurtbox is not
+    advance_to_event(&mut collider, 3.0);
+    assert_eq!(collider.next(), Some((HbEvent::Collide, attack, wall)));
+    assert_eq!(collider.next(), None);
+    advance_through_events(&mut collider, 8.5);
+    assert!(!collider.is_overlapping(0, 1));
+    advance_to_event(&mut collider, 9.0);
+    assert_eq!(collider.next(), Some((HbEvent::Collide, attack, hurtbox)));
+    assert_eq!(collider.next(), None);
+
+    // the pair stays collided until the depth drops below 0.25
+    advance_to_event(&mut collider, 11.75);
+    assert_eq!(collider.next(), Some((HbEvent::Separate, attack, hurtbox)));
+    assert_eq!(collider.next(), None);
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct PaddedHbProfile {
+    id: HbId,
+    padding: Option<f64>,
+}
+
+impl HbProfile for PaddedHbProfile {
+    fn id(&self) -> HbId {
+        self.id
+    }
+    fn padding(&self) -> Option<f64> {
+        self.padding
+    }
+    fn can_interact(&self, _other: &PaddedHbProfile) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_hitbox_padding() {
+    let mut collider = Collider::<PaddedHbProfile>::new(4.0, 0.25);
+    let floor = PaddedHbProfile {
+        id: 0,
+        padding: None,
+    };
+    let player = PaddedHbProfile {
+        id: 1,
+        padding: Some(1.0),
+    };
+    let bullet_1 = PaddedHbProfile {
+        id: 2,
+        padding: Some(0.01),
+    };
+    let bullet_2 = PaddedHbProfile {
+        id: 3,
+        padding: Some(0.01),
+    };
+    collider.add_hitbox(
+        floor,
+        Shape::rect(v2(10.0, 1.0)).place(v2(0.0, 0.0)).still(),
+    );
+    collider.add_hitbox(
+        player,
+        Shape::square(1.0).place(v2(0.0, 0.75)).moving(v2(0.0, 1.0)),
+    );
+    collider.add_hitbox(
+        bullet_1,
+        Shape::circle(0.1)
+            .place(v2(20.0, 0.0))
+            .moving(v2(-1.0, 0.0)),
+    );
+    collider.add_hitbox(bullet_2, Shape::circle(0.1).place(v2(20.05, 0.0)).still());
+
+    // the larger padding of each pair is used
+    assert!((collider.next_time() - 0.06).abs() < 1e-9);
+    collider.set_time(collider.next_time());
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Separate, bullet_1, bullet_2))
+    );
+    assert_eq!(collider.next(), None);
+    advance_to_event(&mut collider, 1.25);
+    assert_eq!(collider.next(), Some((HbEvent::Separate, floor, player)));
+    assert_eq!(collider.next(), None);
+}
+
+#[test]
+#[should_panic]
+fn test_hitbox_padding_min_size() {
+    let mut collider = Collider::<PaddedHbProfile>::new(4.0, 0.25);
+    let profile = PaddedHbProfile {
+        id: 0,
+        padding: Some(1.0),
+    };
+    collider.add_hitbox(profile, Shape::square(0.5).place(v2(0.0, 0.0)).still());
+}
+
+#[test]
+fn test_spinning_rect() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    let mut blade = Shape::oriented_rect(v2(6.0, 0.5), 0.0)
+        .place(v2(0.0, 0.0))
+        .still();
+    blade.vel.angular = 1.0;
+    collider.add_hitbox(0.into(), blade);
+    collider.add_hitbox(
+        1.into(),
+        Shape::rect(v2(10.0, 1.0)).place(v2(0.0, 3.0)).still(),
+    );
+
+    let radius = v2(3.0, 0.25).len();
+    let tilt = (0.25f64 / 3.0).atan();
+    advance(&mut collider, 0.5);
+    let time = collider.next_time();
+    assert!((time - ((2.5 / radius).asin() - tilt)).abs() < 1e-6);
+    collider.set_time(time);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 1.into()))
+    );
+    assert_eq!(collider.next(), None);
+    let blade = collider.get_hitbox(0);
+    assert!((blade.value.shape.angle() - time).abs() < 1e-9);
+    let contact = blade.value.contact_point(&collider.get_hitbox(1).value);
+    assert!((contact - v2(3.0, 0.25).rotate(time)).len() < 1e-6);
+
+    advance(&mut collider, 2.0);
+    let time = collider.next_time();
+    assert!((time - (f64::consts::PI - (2.25 / radius).asin() + tilt)).abs() < 1e-6);
+    collider.set_time(time);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Separate, 0.into(), 1.into()))
+    );
+    assert_eq!(collider.next(), None);
+}
+
+#[test]
+fn test_polygon_ramp() {
+    static RAMP: [Vec2; 3] = [
+        Vec2 { x: -2.0, y: -1.0 },
+        Vec2 { x: 2.0, y: -1.0 },
+        Vec2 { x: 2.0, y: 1.0 },
+    ];
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(0.into(), Shape::polygon(&RAMP).place(v2(0.0, 0.0)).still());
+    collider.add_hitbox(
+        1.into(),
+        Shape::square(1.0).place(v2(0.0, 3.0)).moving(v2(0.0, -1.0)),
+    );
+
+    // the square lands on the slope with its bottom-right corner
+    advance_to_event(&mut collider, 2.25);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 1.into()))
+    );
+    assert_eq!(collider.next(), None);
+    let contact = collider
+        .get_hitbox(0)
+        .value
+        .contact_point(&collider.get_hitbox(1).value);
+    assert!((contact - v2(0.5, 0.25)).len() < 1e-9);
+}
+
+#[test]
+fn test_stretching_circle() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    let mut blob = Shape::circle(2.0).place(v2(0.0, 0.0)).still();
+    blob.vel.resize = v2(2.0, -0.5);
+    collider.add_hitbox(0.into(), blob);
+    collider.add_hitbox(1.into(), Shape::square(2.0).place(v2(4.0, 0.0)).still());
+
+    advance(&mut collider, 1.0);
+    let time = collider.next_time();
+    assert!((time - 2.0).abs() < 1e-6);
+    collider.set_time(time);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 1.into()))
+    );
+    assert_eq!(collider.next(), None);
+    let blob = collider.get_hitbox(0);
+    assert!((blob.value.dims() - v2(6.0, 1.0)).len() < 1e-6);
+    let contact = blob.value.contact_point(&collider.get_hitbox(1).value);
+    assert!((contact - v2(3.0, 0.0)).len() < 1e-6);
+}
+
+#[test]
+fn test_half_planes() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(
+        0.into(),
+        Shape::half_plane(Card::PlusY.into())
+            .place(v2(0.0, -10.0))
+            .still(),
+    );
+    collider.add_hitbox(
+        1.into(),
+        Shape::half_plane(v2(-1.0, 0.0))
+            .place(v2(1000.0, 0.0))
+            .still(),
+    );
+    collider.add_hitbox(
+        2.into(),
+        Shape::square(2.0)
+            .place(v2(990.0, 0.0))
+            .moving(v2(1.0, -1.0)),
+    );
+
+    // far from any cell of the moving square, the half-planes are still found
+    advance_to_event(&mut collider, 9.0);
+    let mut events = vec![collider.next().unwrap(), collider.next().unwrap()];
+    events.sort_by_key(|&(_, profile_1, _)| profile_1);
+    assert_eq!(
+        events,
+        vec![
+            (HbEvent::Collide, 0.into(), 2.into()),
+            (HbEvent::Collide, 1.into(), 2.into()),
+        ]
+    );
+    assert_eq!(collider.next(), None);
+
+    collider.set_hitbox_vel(2, HbVel::moving(v2(-1.0, 0.0)));
+    advance_to_event(&mut collider, 9.25);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Separate, 1.into(), 2.into()))
+    );
+    assert_eq!(collider.next(), None);
+
+    let overlaps = collider.remove_hitbox(0);
+    assert_eq!(overlaps, vec![2.into()]);
+    let overlaps = collider.add_hitbox(
+        3.into(),
+        Shape::half_plane(Card::PlusX.into())
+            .place(v2(0.0, 0.0))
+            .still(),
+    );
+    assert!(overlaps.is_empty());
+    let query = Shape::square(2.0).place(v2(-5.0, 0.0));
+    assert_eq!(collider.query_overlaps(&query, &0.into()), vec![3.into()]);
+}
+
+#[test]
+fn test_hitbox_path() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(0.into(), Shape::square(2.0).place(v2(0.0, 0.0)).still());
+    collider.add_hitbox(1.into(), Shape::square(2.0).place(v2(4.0, 5.5)).still());
+    collider.set_hitbox_path(0, &[(2.0, v2(4.0, 0.0)), (4.0, v2(4.0, 4.0))]);
+    assert_eq!(collider.get_hitbox(0).vel.value, v2(2.0, 0.0));
+
+    // the velocity is switched at the first waypoint
+    advance(&mut collider, 2.0);
+    assert!(collider.next().is_none());
+    let hitbox = collider.get_hitbox(0);
+    assert_eq!(hitbox.value.pos, v2(4.0, 0.0));
+    assert_eq!(hitbox.vel.value, v2(0.0, 2.0));
+    assert_eq!(hitbox.vel.end_time, 4.0);
+
+    advance_to_event(&mut collider, 3.75);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 1.into()))
+    );
+    assert_eq!(collider.next(), None);
+
+    // the hitbox stops at the last waypoint
+    advance(&mut collider, 10.0);
+    let hitbox = collider.get_hitbox(0);
+    assert_eq!(hitbox.value.pos, v2(4.0, 4.0));
+    assert_eq!(hitbox.vel.value, Vec2::zero());
+    assert_eq!(collider.next_time(), f64::INFINITY);
+
+    // setting the velocity stops the hitbox from following a path
+    collider.set_hitbox_path(0, &[(12.0, v2(4.0, 0.0)), (20.0, v2(0.0, 0.0))]);
+    collider.set_hitbox_vel(0, HbVel::moving(v2(-1.0, 0.0)));
+    advance_to_event(&mut collider, 12.25);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Separate, 0.into(), 1.into()))
+    );
+    assert_eq!(collider.next(), None);
+    assert_eq!(collider.get_hitbox(0).vel.value, v2(-1.0, 0.0));
+}
+
+#[test]
+fn test_attached_hitboxes() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(0.into(), Shape::square(2.0).place(v2(0.0, 0.0)).still());
+    collider.add_hitbox(1.into(), Shape::circle(1.0).place(v2(50.0, 0.0)).still());
+    collider.add_hitbox(2.into(), Shape::square(1.0).place(v2(50.0, 50.0)).still());
+    collider.add_hitbox(3.into(), Shape::square(2.0).place(v2(10.0, 0.0)).still());
+    collider.attach_hitbox(1, 0, v2(3.0, 0.0));
+    collider.attach_hitbox(2, 1, v2(0.0, 2.0));
+    assert_eq!(collider.get_parent(2), Some(1.into()));
+    assert_eq!(collider.get_hitbox(1).value.pos, v2(3.0, 0.0));
+    assert_eq!(collider.get_hitbox(2).value.pos, v2(3.0, 2.0));
+
+    // the children move along with the parent
+    collider.set_hitbox_vel(0, HbVel::moving(v2(1.0, 0.0)));
+    assert_eq!(collider.get_hitbox(2).vel.value, v2(1.0, 0.0));
+    advance_to_event(&mut collider, 5.5);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 1.into(), 3.into()))
+    );
+    assert_eq!(collider.next(), None);
+    collider.set_hitbox_pos(0, v2(0.0, 0.0));
+    assert_eq!(collider.get_hitbox(2).value.pos, v2(3.0, 2.0));
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Separate, 1.into(), 3.into()))
+    );
+    assert_eq!(collider.next(), None);
+
+    // removing the parent detaches its children
+    collider.remove_hitbox(0);
+    assert_eq!(collider.get_parent(1), None);
+    collider.set_hitbox_vel(1, HbVel::still());
+    assert_eq!(collider.get_hitbox(2).vel.value, Vec2::zero());
+}
+
+#[test]
+#[should_panic]
+fn test_set_attached_vel() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(0.into(), Shape::square(2.0).place(v2(0.0, 0.0)).still());
+    collider.add_hitbox(1.into(), Shape::square(2.0).place(v2(0.0, 0.0)).still());
+    collider.attach_hitbox(1, 0, v2(3.0, 0.0));
+    collider.set_hitbox_vel(1, HbVel::moving(v2(1.0, 0.0)));
+}
+
+#[test]
+#[should_panic]
+fn test_attach_cycle() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(0.into(), Shape::square(2.0).place(v2(0.0, 0.0)).still());
+    collider.add_hitbox(1.into(), Shape::square(2.0).place(v2(0.0, 0.0)).still());
+    collider.attach_hitbox(1, 0, v2(3.0, 0.0));
+    collider.attach_hitbox(0, 1, v2(3.0, 0.0));
+}
+
+#[test]
+fn test_orbit() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(
+        0.into(),
+        Shape::circle(2.0)
+            .place(v2(10.0, 0.0))
+            .orbiting(v2(0.0, 0.0), 0.5),
+    );
+    collider.add_hitbox(1.into(), Shape::square(2.0).place(v2(-8.0, 3.0)).still());
+
+    // the circle comes down onto the top of the square
+    let expected = (f64::consts::PI * 5.0 / 6.0) / 0.5;
+    advance(&mut collider, expected - 1e-3);
+    let time = collider.next_time();
+    assert!((time - expected).abs() < 1e-6);
+    collider.set_time(time);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 1.into()))
+    );
+    assert_eq!(collider.next(), None);
+    let hitbox = collider.get_hitbox(0);
+    assert!((hitbox.value.pos.len() - 10.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_next_event_for() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.25);
+    collider.add_hitbox(0.into(), Shape::square(2.0).place(v2(0.0, 0.0)).still());
+    collider.add_hitbox(
+        1.into(),
+        Shape::square(2.0).place(v2(5.0, 0.0)).moving(v2(-1.0, 0.0)),
+    );
+    collider.add_hitbox(2.into(), Shape::square(2.0).place(v2(0.0, 20.0)).still());
+    assert_eq!(
+        collider.next_event_for(0),
+        Some((3.0, HbEvent::Collide, 1.into()))
+    );
+    assert_eq!(
+        collider.next_event_for(1),
+        Some((3.0, HbEvent::Collide, 0.into()))
+    );
+    assert_eq!(collider.next_event_for(2), None);
+
+    advance_to_event(&mut collider, 3.0);
+    assert_eq!(
+        collider.next(),
+        Some((HbEvent::Collide, 0.into(), 1.into()))
+    );
+    assert_eq!(collider.next(), None);
+
+    // events are solved once the hitbox reaches a new cell of the grid
+    advance(&mut collider, 5.0);
+    assert_eq!(
+        collider.next_event_for(0),
+        Some((7.25, HbEvent::Separate, 1.into()))
+    );
+}
+
+#[test]
+fn test_next_event_for_one_way_platform() {
+    let mut collider = Collider::<PlatformHbProfile>::new(4.0, 0.25);
+
+    let platform = PlatformHbProfile {
+        id: 0,
+        solid_sides: Card::PlusY.into(),
+    };
+    let player = PlatformHbProfile {
+        id: 1,
+        solid_sides: CardMask::full(),
+    };
+    collider.add_hitbox(
+        platform,
+        Shape::rect(v2(4.0, 1.0)).place(v2(0.0, 0.0)).still(),
+    );
+    collider.add_hitbox(
+        player,
+        Shape::square(1.0).place(v2(0.0, -3.0)).moving(v2(0.0, 1.0)),
+    );
+
+    // jumping up through the platform will be ignored
+    assert_eq!(collider.next_event_for(0), None);
+    assert_eq!(collider.next_event_for(1), None);
+    advance(&mut collider, 5.0);
+
+    collider.set_hitbox_vel(1, HbVel::moving(v2(0.0, -1.0)));
+    assert_eq!(
+        collider.next_event_for(1),
+        Some((6.0, HbEvent::Collide, platform))
+    );
+    advance_to_event(&mut collider, 6.0);
+    assert_eq!(collider.next(), Some((HbEvent::Collide, platform, player)));
+}
+
+// returns every event up to `time`, sorted within each time
+fn all_events<P: HbProfile>(
+    collider: &mut Collider<P>,
+    time: f64,
+) -> Vec<(u64, HbEvent, HbId, HbId)> {
+    let mut result = Vec::new();
+    while collider.time() < time {
+        let mut events = Vec::new();
+        while let Some((event, profile_1, profile_2)) = collider.next() {
+            events.push((
+                collider.time().to_bits(),
+                event,
+                profile_1.id(),
+                profile_2.id(),
+            ));
+        }
+        events.sort_by_key(|&(time, event, id_1, id_2)| {
+            (time, event == HbEvent::Collide, id_1, id_2)
+        });
+        result.extend(events);
+        let new_time = collider.next_time().min(time);
+        collider.set_time(new_time);
+    }
+    result
+}
+
+fn sorted_ids(profiles: &[TestHbProfile]) -> Vec<HbId> {
+    let mut result: Vec<HbId> = profiles.iter().map(|profile| profile.id).collect();
+    result.sort();
+    result
+}
+
+fn run_controller<P: HbProfile>(
+    collider: &mut Collider<P>,
+    controller: &mut CharacterController,
+    time: f64,
+) {
+    while collider.time() < time {
+        let start = collider.time();
+        let frame_end = (start + 0.125).min(time);
+        while collider.next_time() <= frame_end {
+            let next_time = collider.next_time();
+            collider.set_time(next_time);
+            while let Some((event, profile_1, profile_2)) = collider.next() {
+                controller.handle_event(collider, event, &profile_1, &profile_2);
+            }
+        }
+        collider.set_time(frame_end);
+        controller.update(collider, frame_end - start);
+    }
+}
+
+#[test]
+fn test_character_controller() {
+    let mut collider = Collider::<TestHbProfile>::new(4.0, 0.01);
+    collider.add_hitbox(
+        1.into(),
+        Shape::rect(v2(40., 1.)).place(v2(0., -0.5)).still(),
+    );
+    collider.add_hitbox(
+        2.into(),
+        Shape::rect(v2(2., 0.25)).place(v2(4., 0.125)).still(),
+    );
+    collider.add_hitbox(3.into(), Shape::rect(v2(1., 4.)).place(v2(8.5, 2.)).still());
+    collider.add_hitbox(0.into(), Shape::square(1.).place(v2(0., 2.)).still());
+    let mut controller = CharacterController::new(0, 2.0, 5.0, 10.0);
+    controller.step_height = 0.5;
+
+    // falls onto the floor
+    run_controller(&mut collider, &mut controller, 1.0);
+    assert!(controller.is_grounded());
+    assert_eq!(controller.contacts(), vec![1]);
+    let hitbox = collider.get_hitbox(0);
+    assert!((hitbox.value.pos.y - 0.5).abs() < 1e-9);
+    assert_eq!(hitbox.vel.value, v2(0., 0.));
+
+    // steps onto the ledge
+    controller.walk(1.0);
+    run_controller(&mut collider, &mut controller, 3.0);
+    assert!(controller.is_grounded());
+    assert_eq!(controller.contacts(), vec![2]);
+    let hitbox = collider.get_hitbox(0);
+    assert!((hitbox.value.pos.y - 0.75).abs() < 1e-9);
+    assert_eq!(hitbox.vel.value, v2(2., 0.));
+
+    // drops off the ledge and stops at the wall
+    run_controller(&mut collider, &mut controller, 8.0);
+    assert!(controller.is_grounded());
+    let hitbox = collider.get_hitbox(0);
+    assert!(hitbox.value.pos.dist(&v2(7.5, 0.5)) < 1e-9);
+    assert_eq!(hitbox.vel.value, v2(0., 0.));
+
+    // jumps and lands again
+    controller.walk(0.0);
+    assert!(controller.jump());
+    assert!(!controller.jump());
+    run_controller(&mut collider, &mut controller, 8.5);
+    assert!(!controller.is_grounded());
+    assert!(collider.get_hitbox(0).value.pos.y > 1.0);
+    run_controller(&mut collider, &mut controller, 10.0);
+    assert!(controller.is_grounded());
+    assert!((collider.get_hitbox(0).value.pos.y - 0.5).abs() < 1e-9);
+}
+
 //TODO test custom interactivities...
@@ -33,8 +33,34 @@ pub fn quad_root_ascending(a: f64, b: f64, c: f64) -> Option<f64> {
 
 const MIN_TIGHT_SET_CAPACITY: usize = 4;
 
+// the minimum number of items that is worth mapping over with multiple threads
+const MIN_PARALLEL_ITEMS: usize = 64;
+
+// returns true if `par_map` should be used for `len` items
+pub fn use_parallel(len: usize) -> bool {
+    cfg!(feature = "parallel") && len >= MIN_PARALLEL_ITEMS
+}
+
+// maps `f` over `items`, using multiple threads if the `parallel` feature is
+// enabled
+#[cfg(feature = "parallel")]
+pub fn par_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync + Send,
+{
+    use rayon::prelude::*;
+    items.par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+pub fn par_map<T, R, F: Fn(&T) -> R>(items: &[T], f: F) -> Vec<R> {
+    items.iter().map(f).collect()
+}
+
 // a HashSet that will automatically shrink down in capacity to save space
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct TightSet<T: Hash + Eq> {
     set: FnvHashSet<T>,
 }
@@ -50,18 +76,18 @@ impl<T: Hash + Eq> TightSet<T> {
         self.set.insert(value)
     }
 
-    pub fn contains<Q: ?Sized>(&self, value: &Q) -> bool
+    pub fn contains<Q>(&self, value: &Q) -> bool
     where
         T: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         self.set.contains(value)
     }
 
-    pub fn remove<Q: ?Sized>(&mut self, value: &Q) -> bool
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
     where
         T: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         let success = self.set.remove(value);
         if success
@@ -73,11 +99,11 @@ impl<T: Hash + Eq> TightSet<T> {
         success
     }
 
-    pub fn iter(&self) -> hash_set::Iter<T> {
+    pub fn iter(&self) -> hash_set::Iter<'_, T> {
         self.set.iter()
     }
 
-    pub fn drain(&mut self) -> hash_set::Drain<T> {
+    pub fn drain(&mut self) -> hash_set::Drain<'_, T> {
         self.set.drain()
     }
 